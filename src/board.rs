@@ -1,14 +1,27 @@
 use std::cmp;
+use std::convert::TryFrom;
 use std::error;
 use std::fmt;
 use std::iter;
+use std::str;
 
 use itertools::Itertools;
-use rand;
+use rand::{self, Rng};
+
+// The largest board `FromStr` accepts, as self-play data stores sizes in a byte.
+pub const MAX_SIZE: usize = 255;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Error {
     IllegalMove(Move),
+    InvalidNotation,
+    InvalidSide,
+    InvalidPosition,
+    OutOfBounds,
+    GameOver,
+    NotEmpty,
+    Unreachable,
+    TooLarge,
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -16,7 +29,15 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::IllegalMove(ref m) => write!(f, "Error: {:?}: illegal move", m),
+            Error::IllegalMove(ref m) => write!(f, "Error: {}: illegal move", m),
+            Error::InvalidNotation => write!(f, "Error: invalid position notation"),
+            Error::InvalidSide => write!(f, "Error: invalid side"),
+            Error::InvalidPosition => write!(f, "Error: invalid position"),
+            Error::OutOfBounds => write!(f, "Error: off the board"),
+            Error::GameOver => write!(f, "Error: game over"),
+            Error::NotEmpty => write!(f, "Error: cell is not empty"),
+            Error::Unreachable => write!(f, "Error: position cannot arise in play"),
+            Error::TooLarge => write!(f, "Error: board is larger than {}", MAX_SIZE),
         }
     }
 }
@@ -25,12 +46,28 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::IllegalMove(..) => "Illegal move",
+            Error::InvalidNotation => "Invalid position notation",
+            Error::InvalidSide => "Invalid side",
+            Error::InvalidPosition => "Invalid position",
+            Error::OutOfBounds => "Off the board",
+            Error::GameOver => "Game over",
+            Error::NotEmpty => "Cell is not empty",
+            Error::Unreachable => "Position cannot arise in play",
+            Error::TooLarge => "Board is too large",
         }
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             Error::IllegalMove(..) => None,
+            Error::InvalidNotation => None,
+            Error::InvalidSide => None,
+            Error::InvalidPosition => None,
+            Error::OutOfBounds => None,
+            Error::GameOver => None,
+            Error::NotEmpty => None,
+            Error::Unreachable => None,
+            Error::TooLarge => None,
         }
     }
 }
@@ -57,6 +94,16 @@ impl fmt::Display for Entry {
 impl Entry {
     pub fn is_empty(self) -> bool { self == Entry::Empty }
 
+    // The player's index in seating order, or `None` for cells that are
+    // not players.
+    pub fn seat(self) -> Option<usize> {
+        match self {
+            Entry::Player1 => Some(0),
+            Entry::Player2 => Some(1),
+            _ => None,
+        }
+    }
+
     pub fn flip(self) -> Entry {
         match self {
             Entry::Empty => Entry::Block,
@@ -65,6 +112,24 @@ impl Entry {
             Entry::Player2 => Entry::Player1,
         }
     }
+
+    fn notation(self) -> char {
+        match self {
+            Entry::Empty => '.',
+            Entry::Block => '#',
+            Entry::Player1 => 'x',
+            Entry::Player2 => 'o',
+        }
+    }
+
+    fn from_notation(c: char) -> Option<Entry> {
+        match c {
+            '#' => Some(Entry::Block),
+            'x' => Some(Entry::Player1),
+            'o' => Some(Entry::Player2),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -84,6 +149,46 @@ impl Side {
             Side::West => None,
         }
     }
+
+    pub fn index(self) -> usize {
+        match self {
+            Side::North => 0,
+            Side::East => 1,
+            Side::South => 2,
+            Side::West => 3,
+        }
+    }
+
+    pub fn from_index(i: usize) -> Option<Self> {
+        match i {
+            0 => Some(Side::North),
+            1 => Some(Side::East),
+            2 => Some(Side::South),
+            3 => Some(Side::West),
+            _ => None,
+        }
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'N' | 'n' => Some(Side::North),
+            'E' | 'e' => Some(Side::East),
+            'S' | 's' => Some(Side::South),
+            'W' | 'w' => Some(Side::West),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Side::North => write!(f, "N"),
+            Side::East => write!(f, "E"),
+            Side::South => write!(f, "S"),
+            Side::West => write!(f, "W"),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -93,6 +198,36 @@ pub enum GameState {
     Won,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Entry),
+    Draw,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Win(Entry::Player1) => write!(f, "1-0"),
+            Outcome::Win(Entry::Player2) => write!(f, "0-1"),
+            Outcome::Win(..) => write!(f, "?"),
+            Outcome::Draw => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+impl str::FromStr for Outcome {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Outcome> {
+        match s {
+            "1-0" => Ok(Outcome::Win(Entry::Player1)),
+            "0-1" => Ok(Outcome::Win(Entry::Player2)),
+            "1/2-1/2" => Ok(Outcome::Draw),
+            _ => Err(Error::InvalidNotation),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Board {
     size: usize,
@@ -100,6 +235,17 @@ pub struct Board {
     nlegal: usize,
     state: GameState,
     data: Box<[Entry]>,
+    lines: Vec<Vec<(usize, usize)>>,
+    targets: Box<[Option<Target>]>,
+}
+
+// Where a move from one entry point lands, and whether landing there
+// completes a line for each player. Kept up to date as cells change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Target {
+    row: usize,
+    col: usize,
+    wins: [bool; 2],
 }
 
 impl Board {
@@ -108,17 +254,24 @@ impl Board {
         let active = Entry::Player1;
         let nlegal = size * 4;
         let state = GameState::Ongoing;
-        let data = iter::repeat(Entry::Empty).take(len).collect::<Vec<_>>().into_boxed_slice();
-        Board { size, active, nlegal, state, data }
+        let data = iter::repeat_n(Entry::Empty, len).collect::<Vec<_>>().into_boxed_slice();
+        let targets = iter::repeat_n(None, 4 * size).collect::<Vec<_>>().into_boxed_slice();
+        let mut b = Board { size, active, nlegal, state, data, lines: Vec::new(), targets };
+        for i in 0..4 * size { b.targets[i] = b.compute_target(i); }
+        b
     }
 
     pub fn generate(size: usize, filled: usize) -> Board {
+        Board::generate_with_rng(&mut rand::thread_rng(), size, filled)
+    }
+
+    pub fn generate_with_rng<R: Rng>(rng: &mut R, size: usize, filled: usize) -> Board {
         let mut b = Board::new(size);
-        let mut rng = rand::thread_rng();
-        for i in rand::sample(&mut rng, 0..b.data.len(), filled).into_iter() {
+        for i in rand::sample(rng, 0..b.data.len(), filled).into_iter() {
             let (row, col) = b.pos_for(i);
-            b.set(row, col, Entry::Block);
+            b.set_unchecked(row, col, Entry::Block);
         }
+        if b.nlegal == 0 { b.state = GameState::Drawn; }
         b
     }
 
@@ -126,6 +279,35 @@ impl Board {
 
     pub fn active(&self) -> Entry { self.active }
 
+    pub fn state(&self) -> GameState { self.state }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.state {
+            GameState::Ongoing => None,
+            GameState::Drawn => Some(Outcome::Draw),
+            GameState::Won => Some(Outcome::Win(self.active)),
+        }
+    }
+
+    pub fn winner(&self) -> Option<Entry> {
+        match self.outcome() {
+            Some(Outcome::Win(entry)) => Some(entry),
+            _ => None,
+        }
+    }
+
+    // The lines of four or more that won the game, each listed from one end
+    // to the other. A single move can complete several at once.
+    pub fn winning_lines(&self) -> &[Vec<(usize, usize)>] { &self.lines }
+
+    pub fn styled(&self, style: Style) -> Styled<'_> {
+        Styled { board: self, style }
+    }
+
+    fn is_winning_cell(&self, row: usize, col: usize) -> bool {
+        self.lines.iter().any(|line| line.contains(&(row, col)))
+    }
+
     fn pos_for(&self, index: usize) -> (usize, usize) {
         let row = index / self.size;
         let col = index % self.size;
@@ -137,94 +319,224 @@ impl Board {
     }
 
     pub fn get(&self, row: usize, col: usize) -> Option<Entry> {
-        self.data.get(self.index_for(row, col)).cloned()
+        if row >= self.size || col >= self.size { return None; }
+        Some(self.data[self.index_for(row, col)])
     }
 
+    /// # Safety
+    ///
+    /// `row` and `col` must both be less than `size()`.
     pub unsafe fn get_unchecked(&self, row: usize, col: usize) -> Entry {
         *self.data.get_unchecked(self.index_for(row, col))
     }
 
-    pub fn set(&mut self, row: usize, col: usize, entry: Entry) -> () {
-        let i = self.index_for(row, col);
-        let e = self.data.get_mut(i).unwrap();
-        if e.is_empty() && !entry.is_empty() {
-            if row == 0 || row == (self.size - 1) { self.nlegal -= 1; }
-            if col == 0 || col == (self.size - 1) { self.nlegal -= 1; }
+    // Changes a cell and works out the game state afresh. Changes that
+    // would leave a completed line for the side not to move are refused.
+    pub fn set(&mut self, row: usize, col: usize, entry: Entry) -> Result<()> {
+        let old = self.get(row, col).ok_or(Error::OutOfBounds)?;
+        self.set_unchecked(row, col, entry);
+        if let Err(e) = self.update_state() {
+            self.set_unchecked(row, col, old);
+            return Err(e);
         }
-        *e = entry;
+        Ok(())
     }
 
-    #[inline]
-    fn is_winning_horiz(&self, row: usize, col: usize) -> bool {
-        let mut n = 0;
-        for col1 in 0..self.size {
-            let is_this = col1 == col;
-            let is_active = self.active == unsafe { self.get_unchecked(row, col1) };
-            let is_match = is_this || is_active;
-            if is_match { n += 1; if n >= 4 { return true; } } else { n = 0; }
+    // Like `set`, but refuses to overwrite anything but an empty cell.
+    pub fn place(&mut self, row: usize, col: usize, entry: Entry) -> Result<()> {
+        match self.get(row, col) {
+            None => Err(Error::OutOfBounds),
+            Some(Entry::Empty) => self.set(row, col, entry),
+            Some(..) => Err(Error::NotEmpty),
         }
-        false
     }
 
-    #[inline]
-    fn is_winning_vert(&self, row: usize, col: usize) -> bool {
-        let mut n = 0;
-        for row1 in 0..self.size {
-            let is_this = row1 == row;
-            let is_active = self.active == unsafe { self.get_unchecked(row1, col) };
-            let is_match = is_this || is_active;
-            if is_match { n += 1; if n >= 4 { return true; } } else { n = 0; }
+    fn set_unchecked(&mut self, row: usize, col: usize, entry: Entry) {
+        let i = self.index_for(row, col);
+        let edges = (row == 0 || row == self.size - 1) as usize
+            + (col == 0 || col == self.size - 1) as usize;
+        if self.data[i].is_empty() && !entry.is_empty() { self.nlegal -= edges; }
+        if !self.data[i].is_empty() && entry.is_empty() { self.nlegal += edges; }
+        self.data[i] = entry;
+        self.update_targets(row, col);
+    }
+
+    fn compute_target(&self, i: usize) -> Option<Target> {
+        let m = self.move_for(MoveId::new(i))?;
+        m.target(self).map(|(row, col)| Target { row, col, wins: self.wins_at(row, col) })
+    }
+
+    fn wins_at(&self, row: usize, col: usize) -> [bool; 2] {
+        [self.line_length(row, col, Entry::Player1) >= 4,
+         self.line_length(row, col, Entry::Player2) >= 4]
+    }
+
+    // A change at `row`, `col` moves the landing cells of the four entry
+    // points in line with it, and can only complete or spoil lines through
+    // landing cells within three steps along a row, column or diagonal.
+    fn update_targets(&mut self, row: usize, col: usize) {
+        let n = self.size;
+        for &i in &[col, n + row, 2 * n + col, 3 * n + row] {
+            self.targets[i] = self.compute_target(i);
+        }
+        for i in 0..4 * n {
+            if let Some(t) = self.targets[i] {
+                let dr = (t.row as isize - row as isize).abs();
+                let dc = (t.col as isize - col as isize).abs();
+                if cmp::max(dr, dc) <= 3 && (dr == 0 || dc == 0 || dr == dc) {
+                    self.targets[i] = Some(Target { wins: self.wins_at(t.row, t.col), ..t });
+                }
+            }
         }
-        false
     }
 
-    #[inline]
-    fn is_winning_diag_nw_se(&self, row: usize, col: usize) -> bool {
+    fn run_length(&self, row: usize, col: usize, entry: Entry, dr: isize, dc: isize) -> usize {
         let mut n = 0;
-        let d = cmp::min(row, col);
-        for (row1, col1) in ((row - d)..self.size).zip((col - d)..self.size) {
-            let is_this = row1 == row && col1 == col;
-            let is_active = self.active == unsafe { self.get_unchecked(row1, col1) };
-            let is_match = is_this || is_active;
-            if is_match { n += 1; if n >= 4 { return true; } } else { n = 0; }
+        let (mut r, mut c) = (row as isize + dr, col as isize + dc);
+        while r >= 0 && c >= 0 && (r as usize) < self.size && (c as usize) < self.size
+            && self.data[self.index_for(r as usize, c as usize)] == entry
+        {
+            n += 1;
+            r += dr;
+            c += dc;
         }
-        false
+        n
     }
 
-    #[inline]
-    fn is_winning_diag_sw_ne(&self, row: usize, col: usize) -> bool {
-        let mut n = 0;
-        let d = cmp::min(self.size - row - 1, col);
-        for (row1, col1) in (0..(row + d + 1)).rev().zip((col - d)..self.size) {
-            let is_this = row1 == row && col1 == col;
-            let is_active = self.active == unsafe { self.get_unchecked(row1, col1) };
-            let is_match = is_this || is_active;
-            if is_match { n += 1; if n >= 4 { return true; } } else { n = 0; }
+    // Length of the longest line of `entry` through `row`, `col`, counting
+    // that cell as `entry` whatever it holds.
+    pub fn line_length(&self, row: usize, col: usize, entry: Entry) -> usize {
+        [(0, 1), (1, 0), (1, 1), (1, -1)].iter().map(|&(dr, dc)| {
+            1 + self.run_length(row, col, entry, dr, dc)
+                + self.run_length(row, col, entry, -dr, -dc)
+        }).max().unwrap()
+    }
+
+    fn lines_through(&self, row: usize, col: usize, entry: Entry) -> Vec<Vec<(usize, usize)>> {
+        let mut lines = Vec::new();
+        for &(dr, dc) in &[(0, 1), (1, 0), (1, 1), (1, -1)] {
+            let back = self.run_length(row, col, entry, -dr, -dc) as isize;
+            let len = 1 + back + self.run_length(row, col, entry, dr, dc) as isize;
+            if len < 4 { continue; }
+            let (r0, c0) = (row as isize - back * dr, col as isize - back * dc);
+            lines.push((0..len).map(|k| ((r0 + k * dr) as usize, (c0 + k * dc) as usize))
+                       .collect());
         }
-        false
+        lines
+    }
+
+    // Legal moves that would complete a line of four or more for `player`,
+    // whether or not it is their turn. Moves are annotated for `active()`.
+    pub fn winning_moves(&self, player: Entry) -> Vec<LegalMove> {
+        self.legal_moves_iter().filter(|m| self.line_length(m.row, m.col, player) >= 4).collect()
+    }
+
+    // Moves after which the opponent of `player` has no winning move. Empty
+    // if the opponent has no winning move to stop, or if no move stops all
+    // of them.
+    pub fn must_block(&self, player: Entry) -> Vec<LegalMove> {
+        let opponent = player.flip();
+        if self.winning_moves(opponent).is_empty() { return Vec::new(); }
+        self.legal_moves_iter().filter(|m| {
+            let mut b = self.clone();
+            b.set_unchecked(m.row, m.col, player);
+            b.winning_moves(opponent).is_empty()
+        }).collect()
+    }
+
+    // Runs of exactly three of `player`'s cells with at least one empty cell
+    // extending them.
+    pub fn threes(&self, player: Entry) -> Vec<Three> {
+        let size = self.size as isize;
+        let at = |r: isize, c: isize| {
+            if r < 0 || c < 0 || r >= size || c >= size { return None; }
+            Some(self.data[self.index_for(r as usize, c as usize)])
+        };
+        let mut threes = Vec::new();
+        for i in 0..self.data.len() {
+            if self.data[i] != player { continue; }
+            let (row, col) = self.pos_for(i);
+            let (row, col) = (row as isize, col as isize);
+            for &(dr, dc) in &[(0, 1), (1, 0), (1, 1), (1, -1)] {
+                if at(row - dr, col - dc) == Some(player) { continue; }
+                if (1..3).any(|k| at(row + k * dr, col + k * dc) != Some(player))
+                    || at(row + 3 * dr, col + 3 * dc) == Some(player)
+                {
+                    continue;
+                }
+                let ends = [(row - dr, col - dc), (row + 3 * dr, col + 3 * dc)].iter()
+                    .filter(|&&(r, c)| at(r, c) == Some(Entry::Empty))
+                    .map(|&(r, c)| (r as usize, c as usize))
+                    .collect::<Vec<_>>();
+                if ends.is_empty() { continue; }
+                let cell = |k: isize| ((row + k * dr) as usize, (col + k * dc) as usize);
+                threes.push(Three { cells: [cell(0), cell(1), cell(2)], ends });
+            }
+        }
+        threes
+    }
+
+    // Moves after which `player` could win on two or more different cells,
+    // so a single reply cannot block both. Winning moves are not included.
+    pub fn forks(&self, player: Entry) -> Vec<LegalMove> {
+        self.legal_moves_iter().filter(|m| {
+            if self.line_length(m.row, m.col, player) >= 4 { return false; }
+            let mut b = self.clone();
+            b.set_unchecked(m.row, m.col, player);
+            let mut cells = b.winning_moves(player).iter().map(|m| m.target()).collect::<Vec<_>>();
+            cells.sort();
+            cells.dedup();
+            cells.len() >= 2
+        }).collect()
+    }
+
+    pub fn move_id(&self, m: Move) -> Option<MoveId> {
+        if m.pos >= self.size { return None; }
+        u32::try_from(m.side.index() * self.size + m.pos).ok().map(MoveId)
+    }
+
+    pub fn move_for(&self, id: MoveId) -> Option<Move> {
+        let i = id.index();
+        Side::from_index(i / self.size).map(|side| Move::new(side, i % self.size))
     }
 
-    fn is_winning(&self, row: usize, col: usize) -> bool {
-        self.is_winning_horiz(row, col)
-            || self.is_winning_vert(row, col)
-            || self.is_winning_diag_nw_se(row, col)
-            || self.is_winning_diag_sw_ne(row, col)
+    pub fn legal_move(&self, id: MoveId) -> Option<LegalMove> {
+        let t = (*self.targets.get(id.index())?)?;
+        let is_winning = match self.active {
+            Entry::Player1 => t.wins[0],
+            Entry::Player2 => t.wins[1],
+            _ => false,
+        };
+        let base = self.move_for(id)?;
+        Some(LegalMove { base, id, row: t.row, col: t.col, is_winning })
     }
 
-    pub fn legal_moves_iter(&self) -> LegalMovesIter {
-        LegalMovesIter { board: self, base: Some(Move::new(Side::North, 0)) }
+    pub fn legal_moves_iter(&self) -> LegalMovesIter<'_> {
+        LegalMovesIter { board: self, index: 0 }
+    }
+
+    pub fn parse_move(&self, s: &str) -> Result<LegalMove> {
+        let m = s.parse::<Move>()?;
+        if m.pos >= self.size { return Err(Error::OutOfBounds); }
+        m.annotated(self).ok_or(Error::IllegalMove(m))
     }
 
     pub fn make_move(&mut self, m: Move) -> Result<GameState> {
+        if self.state != GameState::Ongoing { return Err(Error::GameOver); }
+        if m.pos >= self.size { return Err(Error::OutOfBounds); }
         m.annotated(self).map(|m| self.make_legal_move(m)).ok_or(Error::IllegalMove(m))
     }
 
+    // Panics if `m` was not annotated for this position, e.g. if it came
+    // from another board.
     pub fn make_legal_move(&mut self, m: LegalMove) -> GameState {
-        debug_assert!(self.state == GameState::Ongoing);
+        assert!(self.state == GameState::Ongoing, "{}: game over", m);
+        assert!(self.legal_move(m.id) == Some(m), "{}: not a legal move here", m);
         let active = self.active;
-        self.set(m.row, m.col, active);
+        self.set_unchecked(m.row, m.col, active);
         if m.is_winning {
             self.state = GameState::Won;
+            self.lines = self.lines_through(m.row, m.col, active);
         } else if self.nlegal == 0 {
             self.state = GameState::Drawn;
         } else {
@@ -233,40 +545,275 @@ impl Board {
         self.state
     }
 
-    pub fn pass(&mut self) -> () {
-        debug_assert!(self.state == GameState::Ongoing);
+    pub fn pass(&mut self) {
+        assert!(self.state == GameState::Ongoing, "game over");
         self.active = self.active.flip();
     }
+
+    // Rows from north to south separated by `/`, runs of empty cells as
+    // decimal counts, then the side to move, e.g. `4/1#2/x3/4 o`.
+    pub fn to_notation(&self) -> String {
+        let mut s = String::new();
+        for (i, entries) in self.data.iter().chunks(self.size).into_iter().enumerate() {
+            if i > 0 { s.push('/'); }
+            let mut nempty = 0;
+            for &e in entries {
+                if e.is_empty() { nempty += 1; continue; }
+                if nempty > 0 { s.push_str(&nempty.to_string()); nempty = 0; }
+                s.push(e.notation());
+            }
+            if nempty > 0 { s.push_str(&nempty.to_string()); }
+        }
+        s.push(' ');
+        s.push(self.active.notation());
+        s
+    }
+
+    pub fn entries(&self) -> &[Entry] { &self.data }
+
+    pub fn from_entries(size: usize, entries: &[Entry], active: Entry) -> Result<Board> {
+        if entries.len() != size * size { return Err(Error::InvalidNotation); }
+        if active != Entry::Player1 && active != Entry::Player2 {
+            return Err(Error::InvalidNotation);
+        }
+        let mut b = Board::new(size);
+        for (i, &entry) in entries.iter().enumerate() {
+            let (row, col) = b.pos_for(i);
+            b.set_unchecked(row, col, entry);
+        }
+        b.active = active;
+        b.update_state()?;
+        Ok(b)
+    }
+
+    // Works out the state from the cells. Only the side to move can have a
+    // completed line, since a winning move ends the game before the turn
+    // passes.
+    fn update_state(&mut self) -> Result<()> {
+        let lines = |entry: Entry| {
+            let mut lines = Vec::new();
+            for i in 0..self.data.len() {
+                let (row, col) = self.pos_for(i);
+                if self.data[i] == entry { lines.extend(self.lines_through(row, col, entry)); }
+            }
+            lines.sort();
+            lines.dedup();
+            lines
+        };
+        if !lines(self.active.flip()).is_empty() { return Err(Error::Unreachable); }
+        self.lines = lines(self.active);
+        self.state = if !self.lines.is_empty() {
+            GameState::Won
+        } else if self.nlegal == 0 {
+            GameState::Drawn
+        } else {
+            GameState::Ongoing
+        };
+        Ok(())
+    }
+}
+
+impl str::FromStr for Board {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Board> {
+        let mut fields = s.split_whitespace();
+        let (rows, active) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(rows), Some(active), None) => (rows, active),
+            _ => return Err(Error::InvalidNotation),
+        };
+        let active = match active {
+            "x" => Entry::Player1,
+            "o" => Entry::Player2,
+            _ => return Err(Error::InvalidNotation),
+        };
+        let rows = rows.split('/').collect::<Vec<_>>();
+        if rows.len() > MAX_SIZE { return Err(Error::TooLarge); }
+        let mut b = Board::new(rows.len());
+        for (row, cells) in rows.into_iter().enumerate() {
+            let mut col = 0;
+            let mut nempty = 0;
+            for c in cells.chars() {
+                if let Some(d) = c.to_digit(10) {
+                    nempty = nempty * 10 + d as usize;
+                    if nempty > b.size { return Err(Error::InvalidNotation); }
+                    continue;
+                }
+                col += nempty;
+                nempty = 0;
+                let entry = Entry::from_notation(c).ok_or(Error::InvalidNotation)?;
+                if col >= b.size { return Err(Error::InvalidNotation); }
+                b.set_unchecked(row, col, entry);
+                col += 1;
+            }
+            if col + nempty != b.size { return Err(Error::InvalidNotation); }
+        }
+        b.active = active;
+        b.update_state()?;
+        Ok(b)
+    }
 }
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "  ")?;
-        for i in 0..self.size { write!(f, "{: >2}", i)?; }
-        write!(f, "\n")?;
-        for (i, entries) in self.data.iter().chunks(self.size).into_iter().enumerate() {
-            write!(f, "{: >2}", i)?;
-            for e in entries { write!(f, " {}", e)?; }
-            write!(f, "\n")?;
+        self.styled(Style::default()).fmt(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Glyphs {
+    Unicode,
+    Ascii,
+}
+
+// How `Board::styled` draws a board. `last` is highlighted in colour, or
+// bracketed without it; `labels` names the entry point at each edge; and
+// `compact` puts the whole board on one line, rows separated by `/`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+    pub glyphs: Glyphs,
+    pub colour: bool,
+    pub labels: bool,
+    pub compact: bool,
+    pub last: Option<(usize, usize)>,
+}
+
+impl Style {
+    pub fn ascii() -> Style {
+        Style { glyphs: Glyphs::Ascii, ..Style::default() }
+    }
+
+    pub fn with_last(self, m: Option<LegalMove>) -> Style {
+        Style { last: m.map(|m| m.target()), ..self }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style { glyphs: Glyphs::Unicode, colour: false, labels: false, compact: false, last: None }
+    }
+}
+
+pub struct Styled<'a> {
+    board: &'a Board,
+    style: Style,
+}
+
+impl<'a> Styled<'a> {
+    fn write_cell(&self, f: &mut fmt::Formatter, row: usize, col: usize) -> fmt::Result {
+        let e = self.board.data[row * self.board.size + col];
+        let winning = self.board.is_winning_cell(row, col);
+        let glyph = match (self.style.glyphs, e, winning) {
+            (Glyphs::Unicode, Entry::Player1, true) => '◉',
+            (Glyphs::Unicode, Entry::Player2, true) => '◎',
+            (Glyphs::Unicode, e, _) => e.to_string().chars().next().unwrap(),
+            (Glyphs::Ascii, e, true) => e.notation().to_ascii_uppercase(),
+            (Glyphs::Ascii, e, false) => e.notation(),
+        };
+        if !self.style.colour { return write!(f, "{}", glyph); }
+        let colour = match e {
+            Entry::Empty => "2",
+            Entry::Block => "90",
+            Entry::Player1 => "31",
+            Entry::Player2 => "36",
+        };
+        let bold = if winning { ";1" } else { "" };
+        let reverse = if self.style.last == Some((row, col)) { ";7" } else { "" };
+        write!(f, "\x1b[{}{}{}m{}\x1b[0m", colour, bold, reverse, glyph)
+    }
+
+    // Without colour the last move is bracketed, which takes over the
+    // spaces either side of it.
+    fn separator(&self, row: usize, col: usize, width: usize) -> String {
+        let mut sep = vec![' '; width - 1];
+        if !self.style.colour {
+            if col > 0 && self.style.last == Some((row, col - 1)) { sep[0] = ']'; }
+            if self.style.last == Some((row, col)) { sep[width - 2] = '['; }
+        }
+        sep.into_iter().collect()
+    }
+}
+
+impl<'a> fmt::Display for Styled<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.board.size;
+        if self.style.compact {
+            for row in 0..size {
+                if row > 0 { write!(f, "/")?; }
+                for col in 0..size { self.write_cell(f, row, col)?; }
+            }
+            return write!(f, " {}", self.board.active.notation());
+        }
+        let label = |side: Side, i: usize| {
+            if self.style.labels { format!("{}{}", side, i) } else { i.to_string() }
+        };
+        let width = label(Side::North, size.saturating_sub(1)).len() + 1;
+        write!(f, "{:w$}", "", w = width)?;
+        for col in 0..size { write!(f, "{:>w$}", label(Side::North, col), w = width)?; }
+        writeln!(f)?;
+        for row in 0..size {
+            write!(f, "{:>w$}", label(Side::West, row), w = width)?;
+            for col in 0..size {
+                write!(f, "{}", self.separator(row, col, width))?;
+                self.write_cell(f, row, col)?;
+            }
+            let end = self.separator(row, size, 2);
+            if self.style.labels {
+                write!(f, "{}{}", end, label(Side::East, row))?;
+            } else if end != " " {
+                write!(f, "{}", end)?;
+            }
+            writeln!(f)?;
+        }
+        if self.style.labels {
+            write!(f, "{:w$}", "", w = width)?;
+            for col in 0..size { write!(f, "{:>w$}", label(Side::South, col), w = width)?; }
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Three {
+    pub cells: [(usize, usize); 3],
+    pub ends: Vec<(usize, usize)>,
+}
+
+impl Three {
+    pub fn is_open(&self) -> bool { self.ends.len() == 2 }
+}
+
 pub struct LegalMovesIter<'a> {
     board: &'a Board,
-    base: Option<Move>,
+    index: usize,
 }
 
 impl<'a> Iterator for LegalMovesIter<'a> {
     type Item = LegalMove;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.base.and_then(|base| {
-            self.base = base.succ(self.board);
-            base.annotated(self.board).or_else(|| self.next())
-        })
+        while self.index < self.board.targets.len() {
+            let id = MoveId::new(self.index);
+            self.index += 1;
+            if let Some(m) = self.board.legal_move(id) { return Some(m); }
+        }
+        None
+    }
+}
+
+// A move's entry point as a dense index in `0..4 * size`, ordered like
+// `legal_moves_iter`. It stays valid for any board of the same size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MoveId(u32);
+
+impl MoveId {
+    // Indices are below `4 * size`, which fits for any board that fits in memory.
+    pub fn new(index: usize) -> MoveId {
+        MoveId(u32::try_from(index).expect("move index out of range"))
     }
+
+    pub fn index(self) -> usize { self.0 as usize }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -276,18 +823,15 @@ pub struct Move  {
 }
 
 impl Move {
+    // A move does not know the board size; boards check `pos` whenever they
+    // take one, and report `OutOfBounds` if it is off the board.
     pub fn new(side: Side, pos: usize) -> Move {
         Move { side, pos }
     }
 
-    fn succ(&self, b: &Board) -> Option<Move> {
-        let pos = self.pos.wrapping_add(1);
-        if pos < b.size {
-            Some(Move::new(self.side, pos))
-        } else {
-            self.side.succ().map(|side| Move::new(side, 0))
-        }
-    }
+    pub fn side(&self) -> Side { self.side }
+
+    pub fn pos(&self) -> usize { self.pos }
 
     fn origin(&self, b: &Board) -> (usize, usize) {
         match self.side {
@@ -300,7 +844,7 @@ impl Move {
 
     pub fn is_legal(&self, b: &Board) -> bool {
         let (row, col) = self.origin(b);
-        b.get(row, col).map_or(false, Entry::is_empty)
+        b.get(row, col).is_some_and(Entry::is_empty)
     }
 
     fn iter<'a>(&self, b: &'a Board) -> MoveVectorIter<'a> {
@@ -314,15 +858,34 @@ impl Move {
     }
 
     pub fn annotated(&self, b: &Board) -> Option<LegalMove> {
-        self.target(b).map(|(row, col)| {
-            let is_winning = b.is_winning(row, col);
-            LegalMove { base: *self, row, col, is_winning }
-        })
+        b.move_id(*self).and_then(|id| b.legal_move(id))
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.side, self.pos)
+    }
+}
+
+impl str::FromStr for Move {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Move> {
+        let s = s.trim();
+        let mut chars = s.chars();
+        let side = chars.next().and_then(Side::from_char).ok_or(Error::InvalidSide)?;
+        let pos = chars.as_str();
+        if pos.is_empty() || !pos.chars().all(|c| c.is_ascii_digit()) {
+            return Err(Error::InvalidPosition);
+        }
+        let pos = pos.parse::<usize>().map_err(|_| Error::InvalidPosition)?;
+        Ok(Move::new(side, pos))
     }
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct MoveVectorIter<'a> {
+struct MoveVectorIter<'a> {
     board: &'a Board,
     side: Side,
     row: usize,
@@ -352,15 +915,28 @@ impl<'a> Iterator for MoveVectorIter<'a> {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct LegalMove {
     base: Move,
+    id: MoveId,
     row: usize,
     col: usize,
     is_winning: bool,
 }
 
 impl LegalMove {
+    pub fn base(&self) -> Move { self.base }
+
+    pub fn id(&self) -> MoveId { self.id }
+
+    pub fn target(&self) -> (usize, usize) { (self.row, self.col) }
+
     pub fn is_winning(&self) -> bool { self.is_winning }
 }
 
+impl fmt::Display for LegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.base.fmt(f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -391,14 +967,68 @@ mod tests {
     #[test]
     fn board_set_then_get() {
         let mut b = Board::new(10);
-        b.set(5, 7, Entry::Player1);
+        b.set(5, 7, Entry::Player1).unwrap();
         assert_eq!(Some(Entry::Empty), b.get(5, 6));
         assert_eq!(Some(Entry::Empty), b.get(6, 8));
         assert_eq!(Some(Entry::Player1), b.get(5, 7));
-        b.set(5, 7, Entry::Block);
+        b.set(5, 7, Entry::Block).unwrap();
         assert_eq!(Some(Entry::Block), b.get(5, 7));
     }
 
+    #[test]
+    fn board_checked_access() {
+        let mut b = Board::new(4);
+        assert_eq!(None, b.get(0, 4));
+        assert_eq!(None, b.get(4, 0));
+        assert_eq!(Err(Error::OutOfBounds), b.set(0, 4, Entry::Block));
+        assert_eq!(Err(Error::OutOfBounds), b.place(4, 0, Entry::Block));
+        assert_eq!(Ok(()), b.place(0, 3, Entry::Block));
+        assert_eq!(Err(Error::NotEmpty), b.place(0, 3, Entry::Player1));
+        assert_eq!(Ok(()), b.set(0, 3, Entry::Empty));
+        assert_eq!(16, b.legal_moves_iter().count());
+        assert!(!Move::new(Side::North, 4).is_legal(&b));
+        assert_eq!(Err(Error::OutOfBounds), b.make_move(Move::new(Side::North, 4)));
+        assert_eq!(Err(Error::OutOfBounds), b.parse_move("N4"));
+    }
+
+    #[test]
+    fn board_set_updates_state() {
+        let mut b = "x3/x3/x3/4 x".parse::<Board>().unwrap();
+        b.set(3, 0, Entry::Player1).unwrap();
+        assert_eq!(Some(Outcome::Win(Entry::Player1)), b.outcome());
+        assert_eq!(1, b.winning_lines().len());
+        b.set(3, 0, Entry::Empty).unwrap();
+        assert_eq!(GameState::Ongoing, b.state());
+        assert!(b.winning_lines().is_empty());
+        let mut b = "x3/x3/x3/4 o".parse::<Board>().unwrap();
+        assert_eq!(Err(Error::Unreachable), b.set(3, 0, Entry::Player1));
+        assert_eq!(Some(Entry::Empty), b.get(3, 0));
+        assert_eq!(GameState::Ongoing, b.state());
+        let mut b = "#2#/4/4/#2# x".parse::<Board>().unwrap();
+        for &(row, col) in &[(0, 1), (0, 2), (1, 0), (1, 3), (2, 0), (2, 3), (3, 1)] {
+            b.place(row, col, Entry::Block).unwrap();
+            assert_eq!(GameState::Ongoing, b.state());
+        }
+        b.place(3, 2, Entry::Block).unwrap();
+        assert_eq!(GameState::Drawn, b.state());
+    }
+
+    #[test]
+    #[should_panic]
+    fn board_make_foreign_move() {
+        let b = Board::new(4);
+        let m = b.parse_move("N0").unwrap();
+        let mut b1 = "x3/4/4/4 x".parse::<Board>().unwrap();
+        b1.make_legal_move(m);
+    }
+
+    #[test]
+    fn board_make_move_after_game_over() {
+        let mut b = "x3/x3/x3/4 x".parse::<Board>().unwrap();
+        assert_eq!(Ok(GameState::Won), b.make_move(Move::new(Side::South, 0)));
+        assert_eq!(Err(Error::GameOver), b.make_move(Move::new(Side::North, 1)));
+    }
+
     #[test]
     fn board_winning_vert() {
         let mut b = Board::new(10);
@@ -420,10 +1050,10 @@ mod tests {
     #[test]
     fn board_winning_diag_nw_se() {
         let mut b = Board::new(10);
-        b.set(4, 4, Entry::Block);
-        b.set(5, 5, Entry::Block);
-        b.set(6, 6, Entry::Block);
-        b.set(7, 7, Entry::Block);
+        b.set(4, 4, Entry::Block).unwrap();
+        b.set(5, 5, Entry::Block).unwrap();
+        b.set(6, 6, Entry::Block).unwrap();
+        b.set(7, 7, Entry::Block).unwrap();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 4))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 5))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 6))); b.pass();
@@ -433,10 +1063,10 @@ mod tests {
     #[test]
     fn board_winning_diag_sw_ne_1() {
         let mut b = Board::new(10);
-        b.set(4, 7, Entry::Block);
-        b.set(5, 6, Entry::Block);
-        b.set(6, 5, Entry::Block);
-        b.set(7, 4, Entry::Block);
+        b.set(4, 7, Entry::Block).unwrap();
+        b.set(5, 6, Entry::Block).unwrap();
+        b.set(6, 5, Entry::Block).unwrap();
+        b.set(7, 4, Entry::Block).unwrap();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 4))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 5))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 6))); b.pass();
@@ -446,10 +1076,10 @@ mod tests {
     #[test]
     fn board_winning_diag_sw_ne_2() {
         let mut b = Board::new(10);
-        b.set(4, 0, Entry::Block);
-        b.set(3, 1, Entry::Block);
-        b.set(2, 2, Entry::Block);
-        b.set(1, 3, Entry::Block);
+        b.set(4, 0, Entry::Block).unwrap();
+        b.set(3, 1, Entry::Block).unwrap();
+        b.set(2, 2, Entry::Block).unwrap();
+        b.set(1, 3, Entry::Block).unwrap();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 0))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 1))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 2))); b.pass();
@@ -459,10 +1089,10 @@ mod tests {
     #[test]
     fn board_winning_diag_sw_ne_3() {
         let mut b = Board::new(10);
-        b.set(4, 6, Entry::Block);
-        b.set(3, 7, Entry::Block);
-        b.set(2, 8, Entry::Block);
-        b.set(1, 9, Entry::Block);
+        b.set(4, 6, Entry::Block).unwrap();
+        b.set(3, 7, Entry::Block).unwrap();
+        b.set(2, 8, Entry::Block).unwrap();
+        b.set(1, 9, Entry::Block).unwrap();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 6))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 7))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::North, 8))); b.pass();
@@ -472,10 +1102,10 @@ mod tests {
     #[test]
     fn board_winning_diag_sw_ne_4() {
         let mut b = Board::new(10);
-        b.set(8, 6, Entry::Block);
-        b.set(7, 7, Entry::Block);
-        b.set(6, 8, Entry::Block);
-        b.set(5, 9, Entry::Block);
+        b.set(8, 6, Entry::Block).unwrap();
+        b.set(7, 7, Entry::Block).unwrap();
+        b.set(6, 8, Entry::Block).unwrap();
+        b.set(5, 9, Entry::Block).unwrap();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::South, 6))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::South, 7))); b.pass();
         assert_eq!(Ok(GameState::Ongoing), b.make_move(Move::new(Side::South, 9))); b.pass();
@@ -486,13 +1116,13 @@ mod tests {
     fn board_legal_moves_iter() {
         let mut b = Board::new(2);
         assert_eq!(b.nlegal, b.legal_moves_iter().count());
-        b.set(0, 0, Entry::Block);
+        b.set(0, 0, Entry::Block).unwrap();
         assert_eq!(b.nlegal, b.legal_moves_iter().count());
-        b.set(1, 1, Entry::Block);
+        b.set(1, 1, Entry::Block).unwrap();
         assert_eq!(b.nlegal, b.legal_moves_iter().count());
-        b.set(0, 1, Entry::Block);
+        b.set(0, 1, Entry::Block).unwrap();
         assert_eq!(b.nlegal, b.legal_moves_iter().count());
-        b.set(1, 0, Entry::Block);
+        b.set(1, 0, Entry::Block).unwrap();
         assert_eq!(0, b.nlegal);
         assert_eq!(0, b.legal_moves_iter().count());
     }
@@ -500,13 +1130,230 @@ mod tests {
     #[test]
     fn move_is_legal() {
         let mut b = Board::new(2);
-        b.set(0, 0, Entry::Block);
+        b.set(0, 0, Entry::Block).unwrap();
         assert!(Move::new(Side::North, 1).is_legal(&b));
         assert!(!Move::new(Side::North, 0).is_legal(&b));
         assert!(!Move::new(Side::West, 0).is_legal(&b));
         assert!(Move::new(Side::West, 1).is_legal(&b));
     }
 
+    #[test]
+    fn board_notation_round_trip() {
+        let mut b = Board::new(4);
+        b.set(1, 1, Entry::Block).unwrap();
+        b.make_move(Move::new(Side::West, 2)).unwrap();
+        assert_eq!("4/1#2/3x/4 o", b.to_notation());
+        let b1 = "4/1#2/3x/4 o".parse::<Board>().unwrap();
+        assert_eq!(b.to_notation(), b1.to_notation());
+        assert_eq!(Entry::Player2, b1.active());
+        assert_eq!(b.nlegal, b1.nlegal);
+        let b = Board::generate(10, 6);
+        assert_eq!(b.to_notation(), b.to_notation().parse::<Board>().unwrap().to_notation());
+    }
+
+    #[test]
+    fn board_notation_state() {
+        let b = "x3/x3/x3/x3 x".parse::<Board>().unwrap();
+        assert_eq!(GameState::Won, b.state);
+        // Only the side that just moved, and so is still to move, can have won.
+        for s in &["x3/x3/x3/x3 o", "xo2/xo2/xo2/xo2 x"] {
+            assert_eq!(Err(Error::Unreachable), s.parse::<Board>().map(|b| b.state));
+        }
+        let b = "##/## x".parse::<Board>().unwrap();
+        assert_eq!(GameState::Drawn, b.state);
+    }
+
+    #[test]
+    fn board_from_entries() {
+        let b = "x3/1#2/4/3o o".parse::<Board>().unwrap();
+        let b1 = Board::from_entries(4, b.entries(), Entry::Player2).unwrap();
+        assert_eq!(b.to_notation(), b1.to_notation());
+        assert_eq!(b.nlegal, b1.nlegal);
+        assert!(Board::from_entries(3, b.entries(), Entry::Player2).is_err());
+        assert!(Board::from_entries(4, b.entries(), Entry::Block).is_err());
+    }
+
+    #[test]
+    fn board_notation_invalid() {
+        for s in &["", "4/4/4/4", "4/4/4/4 x o", "4/4/4/4 #", "4/4/4 x", "4/4/5/4 x",
+                   "4/4/3/4 x", "4/4/2x2/4 x", "4/4/3.1/4 x", "4/4/40/4 x"] {
+            assert_eq!(Err(Error::InvalidNotation), s.parse::<Board>().map(|b| b.size()));
+        }
+        let rows = vec!["256"; 256].join("/");
+        assert_eq!(Err(Error::TooLarge), format!("{} x", rows).parse::<Board>().map(|b| b.size()));
+        let rows = vec![""; 1 << 20].join("/");
+        assert_eq!(Err(Error::TooLarge), format!("{} x", rows).parse::<Board>().map(|b| b.size()));
+    }
+
+    #[test]
+    fn move_notation() {
+        assert_eq!(Ok(Move::new(Side::North, 4)), "N4".parse());
+        assert_eq!(Ok(Move::new(Side::East, 0)), "E0".parse());
+        assert_eq!(Ok(Move::new(Side::South, 9)), "s9".parse());
+        assert_eq!(Ok(Move::new(Side::West, 12)), " w12\n".parse());
+        assert_eq!(Err(Error::InvalidSide), "".parse::<Move>());
+        assert_eq!(Err(Error::InvalidSide), "x4".parse::<Move>());
+        assert_eq!(Err(Error::InvalidPosition), "N".parse::<Move>());
+        assert_eq!(Err(Error::InvalidPosition), "N+4".parse::<Move>());
+        assert_eq!(Err(Error::InvalidPosition), "N 4".parse::<Move>());
+        for s in &["N4", "E0", "S9", "W12"] {
+            assert_eq!(*s, s.parse::<Move>().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn board_parse_move() {
+        let mut b = Board::new(4);
+        b.set(0, 1, Entry::Block).unwrap();
+        assert_eq!("W3", b.parse_move("w3").unwrap().to_string());
+        assert_eq!(Err(Error::OutOfBounds), b.parse_move("N4"));
+        assert_eq!(Err(Error::IllegalMove(Move::new(Side::North, 1))), b.parse_move("n1"));
+    }
+
+    #[test]
+    fn board_line_length() {
+        let b = "x3/1x2/o1#1/3x x".parse::<Board>().unwrap();
+        assert_eq!(4, b.line_length(2, 2, Entry::Player1));
+        assert_eq!(1, b.line_length(2, 2, Entry::Player2));
+        assert_eq!(2, b.line_length(1, 0, Entry::Player1));
+        assert_eq!(2, b.line_length(3, 0, Entry::Player2));
+        assert_eq!(3, b.line_length(0, 1, Entry::Empty));
+    }
+
+    #[test]
+    fn board_winning_moves() {
+        let b = "4/x3/x3/x1oo x".parse::<Board>().unwrap();
+        let moves = b.winning_moves(Entry::Player1).iter().map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["N0", "E0"], moves);
+        assert!(b.winning_moves(Entry::Player2).is_empty());
+        assert!(b.must_block(Entry::Player1).is_empty());
+        let moves = b.must_block(Entry::Player2).iter().map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["N0", "E0"], moves);
+        // S3 lands at the top of the column, so N3 no longer reaches (4, 3).
+        let b = "o4/5/5/5/xxx1# o".parse::<Board>().unwrap();
+        let moves = b.must_block(Entry::Player2).iter().map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["N3", "S3"], moves);
+        let b = "o4/o4/5/5/1xxx1 o".parse::<Board>().unwrap();
+        assert!(!b.winning_moves(Entry::Player1).is_empty());
+        assert!(b.must_block(Entry::Player2).is_empty());
+    }
+
+    #[test]
+    fn board_threes() {
+        let b = "5/1xxx1/5/o4/o4 o".parse::<Board>().unwrap();
+        let threes = b.threes(Entry::Player1);
+        assert_eq!(1, threes.len());
+        assert_eq!([(1, 1), (1, 2), (1, 3)], threes[0].cells);
+        assert!(threes[0].is_open());
+        let b = "5/oxxx1/5/5/5 o".parse::<Board>().unwrap();
+        let threes = b.threes(Entry::Player1);
+        assert_eq!(vec![(1, 4)], threes[0].ends);
+        assert!(!threes[0].is_open());
+        assert!("5/oxxxo/5/5/5 o".parse::<Board>().unwrap().threes(Entry::Player1).is_empty());
+        assert!(b.threes(Entry::Player2).is_empty());
+    }
+
+    #[test]
+    fn board_forks() {
+        let b = "6/6/1xx3/6/6/6 x".parse::<Board>().unwrap();
+        let forks = b.forks(Entry::Player1).iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["E2"], forks);
+        let b = "6/6/6/6/6/6 x".parse::<Board>().unwrap();
+        assert!(b.forks(Entry::Player1).is_empty());
+    }
+
+    #[test]
+    fn board_winning_lines() {
+        let mut b = "4/x3/x1x1/x1oo x".parse::<Board>().unwrap();
+        assert_eq!(None, b.winner());
+        assert!(b.winning_lines().is_empty());
+        assert_eq!(Ok(GameState::Won), b.make_move(Move::new(Side::East, 0)));
+        assert_eq!(Some(Entry::Player1), b.winner());
+        assert_eq!(&[vec![(0, 0), (1, 0), (2, 0), (3, 0)]], b.winning_lines());
+        assert!(b.to_string().contains(" ◉ · · ·"));
+        let b = "xxxx/x3/x3/x3 x".parse::<Board>().unwrap();
+        assert_eq!(2, b.winning_lines().len());
+        assert_eq!(Some(Outcome::Win(Entry::Player1)), b.outcome());
+        assert_eq!("1-0", Outcome::Win(Entry::Player1).to_string());
+        assert_eq!("?", Outcome::Win(Entry::Block).to_string());
+        assert!("?".parse::<Outcome>().is_err());
+    }
+
+    #[test]
+    fn board_styles() {
+        let b = "x1#o/4/x3/4 o".parse::<Board>().unwrap();
+        let m = b.parse_move("N1").unwrap();
+        let style = Style::ascii().with_last(Some(m));
+        assert_eq!("   0 1 2 3\n 0 x . # o\n 1 . . . .\n 2 x . . .\n 3 .[.]. .\n",
+                   b.styled(style).to_string());
+        let compact = Style { compact: true, ..Style::default() };
+        assert_eq!("●·▓○/····/●···/···· o", b.styled(compact).to_string());
+        let labels = b.styled(Style { labels: true, ..Style::ascii() }).to_string();
+        assert_eq!("    N0 N1 N2 N3", labels.lines().next().unwrap());
+        assert_eq!(" W0  x  .  #  o E0", labels.lines().nth(1).unwrap());
+        assert_eq!("    S0 S1 S2 S3", labels.lines().last().unwrap());
+        let wide = Board::new(11).styled(Style { labels: true, ..Style::ascii() }).to_string();
+        assert!(wide.lines().next().unwrap().ends_with("  N8  N9 N10"));
+        assert!(wide.lines().nth(11).unwrap().starts_with(" W10   .   ."));
+        let plain = Board::new(11).styled(Style::ascii()).to_string();
+        assert!(plain.lines().next().unwrap().ends_with("  8  9 10"));
+        let colour = b.styled(Style { colour: true, ..style }).to_string();
+        assert!(colour.contains("\x1b[31mx\x1b[0m"));
+        assert!(colour.contains("\x1b[2;7m.\x1b[0m"));
+        assert_eq!(Board::new(3).to_string(), Board::new(3).styled(Style::default()).to_string());
+    }
+
+    #[test]
+    fn board_move_ids() {
+        let b = "4/1#2/3x/4 o".parse::<Board>().unwrap();
+        let ids = b.legal_moves_iter().map(|m| m.id()).collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        for m in b.legal_moves_iter() {
+            assert_eq!(Some(m), b.legal_move(m.id()));
+            assert_eq!(Some(m.id()), b.move_id(m.base()));
+        }
+        let id = b.move_id(Move::new(Side::East, 2)).unwrap();
+        assert_eq!(6, id.index());
+        assert_eq!(None, b.legal_move(id));
+        assert_eq!(None, b.move_id(Move::new(Side::West, 4)));
+        assert_eq!(None, b.legal_move(MoveId::new(16)));
+        assert_eq!(70_000, MoveId::new(70_000).index());
+    }
+
+    #[test]
+    fn board_incremental_targets() {
+        let mut rng: rand::StdRng = rand::SeedableRng::from_seed(&[3][..]);
+        for _ in 0..20 {
+            let mut b = Board::generate_with_rng(&mut rng, 7, 5);
+            while b.state() == GameState::Ongoing {
+                let moves = b.legal_moves_iter().collect::<Vec<_>>();
+                let mut n = 0;
+                for side in &[Side::North, Side::East, Side::South, Side::West] {
+                    for pos in 0..b.size() {
+                        let m = Move::new(*side, pos);
+                        let target = m.target(&b);
+                        assert_eq!(target.is_some(), m.is_legal(&b));
+                        if let Some((row, col)) = target {
+                            let lm = moves[n];
+                            n += 1;
+                            assert_eq!(m, lm.base());
+                            assert_eq!((row, col), lm.target());
+                            let mut b1 = b.clone();
+                            let won = b1.make_legal_move(lm) == GameState::Won;
+                            assert_eq!(won, lm.is_winning());
+                        }
+                    }
+                }
+                assert_eq!(moves.len(), n);
+                let m = moves[rng.gen_range(0, moves.len())];
+                b.make_legal_move(m);
+            }
+        }
+    }
+
     #[test]
     fn legal_move_is_winning() {
         let mut b = Board::new(4);
@@ -0,0 +1,348 @@
+// An opening book: weighted moves for early positions, so an engine can
+// skip the search on layouts it has studied. Positions are keyed by a hash
+// of their canonical form, the smallest of the eight rotations and
+// reflections of the board, and moves are stored in that canonical frame.
+// On disk a book is text with one `hash move weight` line per move.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use board::{Board, Entry, LegalMove, Move, Side};
+use player::{ChildStats, MCTSPlayer};
+use selfplay::Sample;
+
+// One of the eight symmetries of the square: a transpose if bit 2 is set,
+// followed by the low two bits' worth of quarter turns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symmetry(u8);
+
+impl Symmetry {
+    pub fn all() -> impl Iterator<Item = Symmetry> { (0..8).map(Symmetry) }
+
+    // Cells just off the board stand for the entry points beside them, so
+    // `row` and `col` range over `-1..=size`.
+    fn apply(self, size: usize, row: isize, col: isize) -> (isize, isize) {
+        let last = size as isize - 1;
+        let (mut r, mut c) = if self.0 & 4 != 0 { (col, row) } else { (row, col) };
+        for _ in 0..self.0 & 3 {
+            let turned = (c, last - r);
+            r = turned.0;
+            c = turned.1;
+        }
+        (r, c)
+    }
+
+    pub fn cell(self, size: usize, row: usize, col: usize) -> (usize, usize) {
+        let (r, c) = self.apply(size, row as isize, col as isize);
+        (r as usize, c as usize)
+    }
+
+    pub fn transform(self, size: usize, m: Move) -> Move {
+        let (pos, n) = (m.pos() as isize, size as isize);
+        let (row, col) = match m.side() {
+            Side::North => (-1, pos),
+            Side::East => (pos, n),
+            Side::South => (n, pos),
+            Side::West => (pos, -1),
+        };
+        match self.apply(size, row, col) {
+            (-1, c) => Move::new(Side::North, c as usize),
+            (r, c) if c == n => Move::new(Side::East, r as usize),
+            (r, c) if r == n => Move::new(Side::South, c as usize),
+            (r, _) => Move::new(Side::West, r as usize),
+        }
+    }
+}
+
+fn cell_char(e: Entry) -> u8 {
+    match e {
+        Entry::Empty => b'.',
+        Entry::Block => b'#',
+        Entry::Player1 => b'x',
+        Entry::Player2 => b'o',
+    }
+}
+
+// FNV-1a, which unlike the std hashers is stable across releases.
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// The hash of `b`'s canonical form, and the symmetries that take `b` there.
+// There is more than one when `b` is itself symmetric.
+pub fn canonical(b: &Board) -> (u64, Vec<Symmetry>) {
+    let size = b.size();
+    let mut best: Option<(Vec<u8>, Vec<Symmetry>)> = None;
+    for sym in Symmetry::all() {
+        let mut cells = vec![0; size * size + 1];
+        for row in 0..size {
+            for col in 0..size {
+                let (r, c) = sym.cell(size, row, col);
+                cells[r * size + c] = cell_char(b.get(row, col).unwrap());
+            }
+        }
+        cells[size * size] = cell_char(b.active());
+        match best {
+            Some((ref best, ref mut syms)) if cells == *best => syms.push(sym),
+            Some((ref best, _)) if cells > *best => (),
+            _ => best = Some((cells, vec![sym])),
+        }
+    }
+    let (cells, syms) = best.unwrap();
+    (fnv(&cells), syms)
+}
+
+fn stones(b: &Board) -> usize {
+    let size = b.size();
+    (0..size * size).filter(|&i| {
+        matches!(b.get(i / size, i % size), Some(Entry::Player1) | Some(Entry::Player2))
+    }).count()
+}
+
+// The weight of a move the search proved to win, more than any search's
+// visit count.
+const PROVEN: u64 = 1 << 32;
+
+// The moves worth keeping from a search: only the proven wins if there are
+// any, and otherwise everything but the proven losses, weighted by visits.
+fn playable(children: &[ChildStats]) -> Vec<(LegalMove, u64)> {
+    let wins = children.iter().filter(|c| c.expected >= 1.0).map(|c| (c.m, PROVEN))
+        .collect::<Vec<_>>();
+    if !wins.is_empty() { return wins; }
+    children.iter().filter(|c| c.expected > 0.0).map(|c| (c.m, c.visits as u64)).collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: HashMap<u64, Vec<(Move, u64)>>,
+}
+
+impl Book {
+    pub fn new() -> Book { Book::default() }
+
+    // The number of positions in the book.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn add(&mut self, b: &Board, m: Move, weight: u64) {
+        if weight == 0 { return; }
+        let (hash, syms) = canonical(b);
+        let m = syms[0].transform(b.size(), m);
+        let moves = self.entries.entry(hash).or_default();
+        match moves.iter_mut().find(|&&mut (m1, _)| m1 == m) {
+            Some(&mut (_, ref mut w)) => *w = w.saturating_add(weight),
+            None => moves.push((m, weight)),
+        }
+    }
+
+    // Adds the search's playable moves for samples with at most `plies`
+    // stones on the board.
+    pub fn add_sample(&mut self, sample: &Sample, plies: usize) {
+        if stones(&sample.board) > plies { return; }
+        for (m, w) in playable(&sample.children) { self.add(&sample.board, m.base(), w); }
+    }
+
+    // Searches `b` with `player` and adds the playable moves, then does the
+    // same `plies - 1` deep below the `width` heaviest of them.
+    pub fn explore(&mut self, player: &MCTSPlayer, b: &Board, plies: usize, width: usize) {
+        if plies == 0 || b.outcome().is_some() { return; }
+        let (_, children) = player.analyse(b);
+        let mut moves = playable(&children);
+        for &(m, w) in &moves { self.add(b, m.base(), w); }
+        moves.sort_by_key(|&(_, w)| !w);
+        for &(m, _) in moves.iter().take(width) {
+            let mut b1 = b.clone();
+            b1.make_legal_move(m);
+            self.explore(player, &b1, plies - 1, width);
+        }
+    }
+
+    // The book moves for `b` with their weights, in `legal_moves_iter` order.
+    pub fn probe(&self, b: &Board) -> Vec<(LegalMove, u64)> {
+        let (hash, syms) = canonical(b);
+        let moves = match self.entries.get(&hash) {
+            Some(moves) => moves,
+            None => return Vec::new(),
+        };
+        b.legal_moves_iter().filter_map(|m| {
+            syms.iter().filter_map(|sym| {
+                let key = sym.transform(b.size(), m.base());
+                moves.iter().find(|&&(m1, _)| m1 == key)
+            }).next().map(|&(_, w)| (m, w))
+        }).collect()
+    }
+
+    // Picks a book move with probability proportional to its weight.
+    pub fn choose<R: Rng>(&self, b: &Board, rng: &mut R) -> Option<LegalMove> {
+        let moves = self.probe(b);
+        let total = moves.iter().fold(0u64, |sum, &(_, w)| sum.saturating_add(w));
+        if total == 0 { return None; }
+        let mut x = rng.gen_range(0, total);
+        for &(m, w) in &moves {
+            if x < w { return Some(m); }
+            x -= w;
+        }
+        None
+    }
+
+    pub fn read<R: BufRead>(r: R) -> io::Result<Book> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut book = Book::new();
+        for line in r.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            if words.len() != 3 { return Err(invalid("expected `hash move weight`")); }
+            let hash = u64::from_str_radix(words[0], 16).map_err(|_| invalid("invalid hash"))?;
+            let m = words[1].parse::<Move>().map_err(|_| invalid("invalid move"))?;
+            let weight = words[2].parse::<u64>().map_err(|_| invalid("invalid weight"))?;
+            book.entries.entry(hash).or_default().push((m, weight));
+        }
+        Ok(book)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Book::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write!(File::create(path)?, "{}", self)
+    }
+}
+
+impl fmt::Display for Book {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut hashes = self.entries.keys().collect::<Vec<_>>();
+        hashes.sort();
+        for hash in hashes {
+            for &(m, w) in &self.entries[hash] {
+                writeln!(f, "{:016x} {} {}", hash, m, w)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use player::Budget;
+
+    fn stats(b: &Board, m: &str, expected: f64, visits: usize) -> ChildStats {
+        ChildStats { m: b.parse_move(m).unwrap(), expected, visits }
+    }
+
+    // Builds the transformed position through its notation, which keeps
+    // the side to move.
+    fn transformed(b: &Board, sym: Symmetry) -> Board {
+        let size = b.size();
+        let mut cells = vec![vec!['.'; size]; size];
+        for row in 0..size {
+            for col in 0..size {
+                let (r, c) = sym.cell(size, row, col);
+                cells[r][c] = cell_char(b.get(row, col).unwrap()) as char;
+            }
+        }
+        let rows = cells.iter().map(|row| {
+            let mut s = String::new();
+            let mut empty = 0;
+            for &c in row {
+                if c == '.' { empty += 1; continue; }
+                if empty > 0 { s.push_str(&empty.to_string()); empty = 0; }
+                s.push(c);
+            }
+            if empty > 0 { s.push_str(&empty.to_string()); }
+            s
+        }).collect::<Vec<_>>();
+        let active = cell_char(b.active()) as char;
+        format!("{} {}", rows.join("/"), active).parse().unwrap()
+    }
+
+    #[test]
+    fn book_symmetries() {
+        let b = "4/1#2/3x/4 o".parse::<Board>().unwrap();
+        let (hash, _) = canonical(&b);
+        for sym in Symmetry::all() {
+            let b1 = transformed(&b, sym);
+            assert_eq!(hash, canonical(&b1).0);
+            for m in b.legal_moves_iter() {
+                let m1 = b1.legal_move(b1.move_id(sym.transform(4, m.base())).unwrap()).unwrap();
+                let (row, col) = m.target();
+                assert_eq!(sym.cell(4, row, col), m1.target());
+            }
+        }
+        let flipped = "4/1#2/3x/4 x".parse::<Board>().unwrap();
+        assert_ne!(hash, canonical(&flipped).0);
+    }
+
+    #[test]
+    fn book_probe_across_symmetries() {
+        let b = "4/1#2/4/4 x".parse::<Board>().unwrap();
+        let mut book = Book::new();
+        book.add(&b, Move::new(Side::North, 0), 3);
+        book.add(&b, Move::new(Side::North, 0), 2);
+        book.add(&b, Move::new(Side::West, 2), 1);
+        assert_eq!(1, book.len());
+        let probed = book.probe(&b).into_iter().map(|(m, w)| (m.to_string(), w))
+            .collect::<Vec<_>>();
+        // The layout is symmetric about the diagonal, so W0 and N2 are the
+        // same moves as N0 and W2.
+        let expected = [("N0", 5), ("N2", 1), ("W0", 5), ("W2", 1)];
+        assert_eq!(expected.iter().map(|&(m, w)| (String::from(m), w)).collect::<Vec<_>>(), probed);
+        let mirrored = "4/2#1/4/4 x".parse::<Board>().unwrap();
+        let probed = book.probe(&mirrored).into_iter().map(|(m, w)| (m.to_string(), w))
+            .collect::<Vec<_>>();
+        let expected = [("N1", 1), ("N3", 5), ("E0", 5), ("E2", 1)];
+        assert_eq!(expected.iter().map(|&(m, w)| (String::from(m), w)).collect::<Vec<_>>(), probed);
+        let book1 = Book::read(book.to_string().as_bytes()).unwrap();
+        assert_eq!(book, book1);
+        assert!(Book::read("xyz N0 1\n".as_bytes()).is_err());
+        assert!(book.probe(&Board::new(4)).is_empty());
+    }
+
+    #[test]
+    fn book_explore_and_choose() {
+        let mut rng = StdRng::from_seed(&[3]);
+        let b = Board::generate_with_rng(&mut rng, 6, 3);
+        let player = MCTSPlayer::with_budget(Budget::Iterations(100));
+        let mut book = Book::new();
+        book.explore(&player, &b, 2, 2);
+        assert_eq!(3, book.len());
+        let m = book.choose(&b, &mut rng).unwrap();
+        assert!(book.probe(&b).iter().any(|&(m1, _)| m1 == m));
+    }
+
+    #[test]
+    fn book_keeps_proven_wins_only() {
+        let b = "xxx2/5/5/5/oo1o1 x".parse::<Board>().unwrap();
+        let mut book = Book::new();
+        let children = vec![stats(&b, "E0", 1.0, 3), stats(&b, "S4", 0.6, 900)];
+        book.add_sample(&Sample { board: b.clone(), children, result: 1.0 }, 6);
+        assert_eq!(vec![(b.parse_move("E0").unwrap(), PROVEN)], book.probe(&b));
+        let b = "4/1#2/3x/4 o".parse::<Board>().unwrap();
+        let mut book = Book::new();
+        let children = vec![stats(&b, "N0", 0.0, 50), stats(&b, "N2", 0.4, 20)];
+        book.add_sample(&Sample { board: b.clone(), children, result: 0.0 }, 6);
+        assert_eq!(vec![(b.parse_move("N2").unwrap(), 20)], book.probe(&b));
+    }
+
+    #[test]
+    fn book_explore_proven_win() {
+        let b = "xxx2/5/5/5/oo1o1 x".parse::<Board>().unwrap();
+        let player = MCTSPlayer::with_budget(Budget::Iterations(100));
+        let mut book = Book::new();
+        book.explore(&player, &b, 2, 1);
+        assert_eq!(vec![(b.parse_move("E0").unwrap(), PROVEN)], book.probe(&b));
+        assert_eq!(Some(b.parse_move("E0").unwrap()), book.choose(&b, &mut rand::thread_rng()));
+    }
+}
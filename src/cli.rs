@@ -0,0 +1,729 @@
+use std::process::Command;
+use std::slice;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::{SeedableRng, StdRng};
+
+use zgc4::book::Book;
+use zgc4::board::{Board, Entry, Glyphs, Style};
+use zgc4::eval;
+use zgc4::player::{self, Budget, MCTSPlayer, Player};
+use zgc4::policy::Policy;
+use zgc4::puzzle;
+use zgc4::selfplay;
+use zgc4::tournament::{Config, Sprt};
+
+pub const USAGE: &str = "\
+Usage: zgc4 [COMMAND] [OPTIONS]
+
+Commands:
+  play              play a game on the console (default)
+  protocol          run as an engine speaking the text protocol on stdin/stdout
+  tournament        play a match between two players, or a round robin
+  selfplay          write MCTS self-play training data
+  train             fit a rollout policy to self-play training data
+  serve             serve games against the engine over HTTP/JSON
+  book              build an opening book from self-play data or deep searches
+  puzzles           generate forced-win puzzles, or test the engine on them
+  help              show this message
+
+Board options:
+  --size N          board size (default 10)
+  --blocks N        number of randomly placed blocks (default 6)
+  --block ROW,COL   place a block at ROW,COL; repeatable, replaces --blocks
+  --position POS    start from a position in board notation
+  --seed N          seed for the random block layout
+
+Player options:
+  --p1 TYPE         first seat: human, random, mcts, alphabeta, or ext:COMMAND
+                    for an external protocol engine (default human)
+  --p2 TYPE         second seat (default mcts)
+  --first 1|2       seat which moves first (default 1)
+  --tui             play human seats in a full-screen terminal interface
+  --listen ADDR     host a game for a peer connecting to ADDR, e.g. 0.0.0.0:7070;
+                    --p1 is the local player and --first picks who starts
+  --connect ADDR    join a game hosted at ADDR as the --p1 player
+
+Display options:
+  --ascii           draw the board with ASCII characters only
+  --colour          colour the pieces and highlight the last move
+  --labels          label the entry points around the edge of the board
+  --compact         print the board on a single line
+
+Engine options:
+  --time SECS       per-move time budget for engines (default 5)
+  --playouts N      per-move playout (or node) budget, instead of --time
+  --policy FILE     rollout policy weights for mcts, as written by train
+  --book FILE       opening book for mcts, as written by book
+  --network FILE    network weights to guide mcts with position values and
+                    move priors
+  --network-weight W
+                    share of a leaf's score taken from the network rather
+                    than a rollout, from 0 to 1 (default 0.5)
+
+Tournament options:
+  --player TYPE     add an entrant, as for --p1; repeat for each entrant
+  --pairs N         pairs of games per match, on a fresh layout each (default 50)
+  --sprt ELO0,ELO1  stop a two-player match early once the SPRT decides
+
+Self-play options:
+  --games N         number of games (default 100)
+  --output FILE     training data file to write (required)
+  --random-plies N  opening plies played at random, unrecorded (default 2)
+
+Training options:
+  --input FILE      self-play training data; repeatable (required)
+  --output FILE     policy weights file to write (required)
+  --epochs N        passes of gradient descent (default 200)
+  --rate R          learning rate (default 0.5)
+
+Book options:
+  --input FILE      self-play training data to take opening moves from;
+                    repeatable. Without it, the book is built by searching
+                    the board given by the board options
+  --output FILE     book file to write (required)
+  --plies N         deepest position to include, in stones played (default 4)
+  --width N         moves searched further at each position (default 3)
+
+Puzzle options (boards default to --size 7 --blocks 4):
+  --depth N         moves the winner needs, exactly (default 2)
+  --count N         puzzles to find (default 10)
+  --games N         give up after this many games (default 1000)
+  --selfplay        find puzzles in mcts self-play games instead of random ones
+  --output FILE     puzzle file to write, one `position move depth` per line
+                    (default: print them)
+  --check FILE      instead of generating, report how many puzzles mcts solves
+
+Server options:
+  --host ADDR       address to listen on (default 127.0.0.1)
+  --port N          port to listen on, 0 for any free port (default 8080)
+";
+
+pub type Args<'a> = slice::Iter<'a, String>;
+
+pub fn value<T: FromStr>(args: &mut Args, flag: &str) -> Result<T, String> {
+    let arg = args.next().ok_or_else(|| format!("{}: missing value", flag))?;
+    arg.parse().map_err(|_| format!("{}: invalid value `{}`", flag, arg))
+}
+
+// Settings shared by the engine players.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineOptions {
+    pub budget: Budget,
+    pub policy: Option<Policy>,
+    pub book: Option<Book>,
+    pub network: Option<eval::Network>,
+    pub weight: f64,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            budget: Budget::Time(Duration::new(5, 0)),
+            policy: None,
+            book: None,
+            network: None,
+            weight: 0.5,
+        }
+    }
+}
+
+impl EngineOptions {
+    pub fn parse_flag(&mut self, flag: &str, args: &mut Args) -> Result<bool, String> {
+        match flag {
+            "--time" => {
+                let secs = value::<f64>(args, flag)?;
+                if secs.is_nan() || secs <= 0.0 {
+                    return Err(String::from("--time: must be positive"));
+                }
+                self.budget = Budget::Time(Duration::from_millis((secs * 1000.0) as u64));
+            },
+            "--playouts" => self.budget = Budget::Iterations(value(args, flag)?),
+            "--policy" => {
+                let path = value::<String>(args, flag)?;
+                let policy = Policy::load(&path).map_err(|e| format!("{}: {}", path, e))?;
+                self.policy = Some(policy);
+            },
+            "--book" => {
+                let path = value::<String>(args, flag)?;
+                let book = Book::load(&path).map_err(|e| format!("{}: {}", path, e))?;
+                self.book = Some(book);
+            },
+            "--network" => {
+                let path = value::<String>(args, flag)?;
+                let network = eval::Network::load(&path)
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                self.network = Some(network);
+            },
+            "--network-weight" => {
+                let weight = value::<f64>(args, flag)?;
+                if !(0.0..=1.0).contains(&weight) {
+                    return Err(String::from("--network-weight: must be between 0 and 1"));
+                }
+                self.weight = weight;
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn mcts(&self) -> MCTSPlayer {
+        let mut p = MCTSPlayer::with_budget(self.budget);
+        if let Some(ref policy) = self.policy { p.set_rollout_policy(policy.clone()); }
+        if let Some(ref book) = self.book { p.set_book(book.clone()); }
+        if let Some(ref network) = self.network {
+            p.set_evaluator(Box::new(network.clone()), self.weight);
+        }
+        p
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    Random,
+    Mcts,
+    AlphaBeta,
+    External(String),
+}
+
+impl FromStr for PlayerKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<PlayerKind, ()> {
+        match s {
+            "human" => Ok(PlayerKind::Human),
+            "random" => Ok(PlayerKind::Random),
+            "mcts" => Ok(PlayerKind::Mcts),
+            "alphabeta" => Ok(PlayerKind::AlphaBeta),
+            _ if s.starts_with("ext:") => Ok(PlayerKind::External(String::from(&s[4..]))),
+            _ => Err(()),
+        }
+    }
+}
+
+impl PlayerKind {
+    pub fn player(&self, engine: &EngineOptions) -> Result<Box<dyn Player>, String> {
+        let budget = engine.budget;
+        Ok(match *self {
+            PlayerKind::Human => {
+                let mut p = player::HumanPlayer::new();
+                p.set_hint_budget(budget);
+                Box::new(p)
+            },
+            PlayerKind::Random => Box::new(player::RandomPlayer),
+            PlayerKind::Mcts => Box::new(engine.mcts()),
+            PlayerKind::AlphaBeta => Box::new(player::AlphaBetaPlayer::new(budget)),
+            PlayerKind::External(ref command) => {
+                let mut words = command.split_whitespace();
+                let program = words.next().ok_or_else(|| String::from("ext: missing command"))?;
+                let mut command = Command::new(program);
+                command.args(words);
+                let p = player::ExternalPlayer::spawn(command, Some(budget))
+                    .map_err(|e| format!("{}: {}", program, e))?;
+                Box::new(p)
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoardOptions {
+    size: usize,
+    blocks: usize,
+    layout: Vec<(usize, usize)>,
+    position: Option<String>,
+    seed: Option<usize>,
+}
+
+impl Default for BoardOptions {
+    fn default() -> Self {
+        BoardOptions { size: 10, blocks: 6, layout: Vec::new(), position: None, seed: None }
+    }
+}
+
+impl BoardOptions {
+    pub fn parse_flag(&mut self, flag: &str, args: &mut Args) -> Result<bool, String> {
+        match flag {
+            "--size" => self.size = value(args, flag)?,
+            "--blocks" => self.blocks = value(args, flag)?,
+            "--block" => {
+                let arg = value::<String>(args, flag)?;
+                let mut coords = arg.split(',').map(str::parse::<usize>);
+                match (coords.next(), coords.next(), coords.next()) {
+                    (Some(Ok(row)), Some(Ok(col)), None) => self.layout.push((row, col)),
+                    _ => return Err(format!("{}: invalid value `{}`", flag, arg)),
+                }
+            },
+            "--position" => self.position = Some(value(args, flag)?),
+            "--seed" => self.seed = Some(value(args, flag)?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => SeedableRng::from_seed(&[seed][..]),
+            None => StdRng::new().unwrap(),
+        }
+    }
+
+    pub fn board(&self) -> Result<Board, String> {
+        if let Some(ref position) = self.position {
+            return position.parse().map_err(|e| format!("--position: {}", e));
+        }
+        if self.size < 4 { return Err(String::from("--size: must be at least 4")); }
+        if !self.layout.is_empty() {
+            let mut b = Board::new(self.size);
+            for &(row, col) in &self.layout {
+                b.place(row, col, Entry::Block)
+                    .map_err(|e| format!("--block: {},{}: {}", row, col, e))?;
+            }
+            if b.legal_moves_iter().next().is_none() {
+                return Err(String::from("--block: the blocks close every entry point"));
+            }
+            return Ok(b);
+        }
+        if self.blocks >= self.size * self.size {
+            return Err(String::from("--blocks: must leave a cell empty"));
+        }
+        let b = Board::generate_with_rng(&mut self.rng(), self.size, self.blocks);
+        if b.legal_moves_iter().next().is_none() {
+            return Err(String::from("--blocks: the layout closes every entry point"));
+        }
+        Ok(b)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayOptions {
+    pub board: BoardOptions,
+    pub seats: [PlayerKind; 2],
+    pub first: usize,
+    pub engine: EngineOptions,
+    pub style: Style,
+    pub tui: bool,
+    pub network: Option<Network>,
+}
+
+// A game against a peer over TCP. The local player takes the first seat
+// from `--p1`; when listening, `--first` picks who moves first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Network {
+    Listen(String),
+    Connect(String),
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions {
+            board: BoardOptions::default(),
+            seats: [PlayerKind::Human, PlayerKind::Mcts],
+            first: 1,
+            engine: EngineOptions::default(),
+            style: Style::default(),
+            tui: false,
+            network: None,
+        }
+    }
+}
+
+impl PlayOptions {
+    pub fn parse(args: &[String]) -> Result<PlayOptions, String> {
+        let mut opts = PlayOptions::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if opts.board.parse_flag(flag, &mut args)? { continue; }
+            if opts.engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--p1" => opts.seats[0] = value(&mut args, flag)?,
+                "--p2" => opts.seats[1] = value(&mut args, flag)?,
+                "--tui" => opts.tui = true,
+                "--listen" => opts.network = Some(Network::Listen(value(&mut args, flag)?)),
+                "--connect" => opts.network = Some(Network::Connect(value(&mut args, flag)?)),
+                "--ascii" => opts.style.glyphs = Glyphs::Ascii,
+                "--colour" | "--color" => opts.style.colour = true,
+                "--labels" => opts.style.labels = true,
+                "--compact" => opts.style.compact = true,
+                "--first" => {
+                    opts.first = value(&mut args, flag)?;
+                    if opts.first != 1 && opts.first != 2 {
+                        return Err(String::from("--first: must be 1 or 2"));
+                    }
+                },
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        Ok(opts)
+    }
+
+    // Seats in move order: the first entry plays `Entry::Player1`.
+    pub fn ordered_seats(&self) -> [&PlayerKind; 2] {
+        let (a, b) = if self.first == 1 { (0, 1) } else { (1, 0) };
+        [&self.seats[a], &self.seats[b]]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentOptions {
+    pub board: BoardOptions,
+    pub entrants: Vec<PlayerKind>,
+    pub engine: EngineOptions,
+    pub config: Config,
+}
+
+impl TournamentOptions {
+    pub fn parse(args: &[String]) -> Result<TournamentOptions, String> {
+        let board = BoardOptions::default();
+        let config = Config { size: board.size, blocks: board.blocks, pairs: 50, sprt: None };
+        let mut opts = TournamentOptions {
+            board,
+            entrants: Vec::new(),
+            engine: EngineOptions::default(),
+            config,
+        };
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if opts.engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--size" | "--blocks" | "--seed" => { opts.board.parse_flag(flag, &mut args)?; },
+                "--player" => opts.entrants.push(value(&mut args, flag)?),
+                "--pairs" => opts.config.pairs = value(&mut args, flag)?,
+                "--sprt" => {
+                    let arg = value::<String>(&mut args, flag)?;
+                    let mut elos = arg.split(',').map(str::parse::<f64>);
+                    match (elos.next(), elos.next(), elos.next()) {
+                        (Some(Ok(elo0)), Some(Ok(elo1)), None) if elo0 < elo1 =>
+                            opts.config.sprt = Some(Sprt::new(elo0, elo1)),
+                        _ => return Err(format!("{}: invalid value `{}`", flag, arg)),
+                    }
+                },
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        if opts.entrants.len() < 2 { return Err(String::from("--player: need at least two")); }
+        if opts.entrants.contains(&PlayerKind::Human) {
+            return Err(String::from("--player: human entrants are not supported"));
+        }
+        if opts.entrants.len() > 2 && opts.config.sprt.is_some() {
+            return Err(String::from("--sprt: only for two-player matches"));
+        }
+        opts.board.board()?;
+        opts.config.size = opts.board.size;
+        opts.config.blocks = opts.board.blocks;
+        Ok(opts)
+    }
+
+    pub fn players(&self) -> Result<Vec<Box<dyn Player>>, String> {
+        self.entrants.iter().map(|kind| kind.player(&self.engine)).collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayOptions {
+    pub board: BoardOptions,
+    pub engine: EngineOptions,
+    pub games: usize,
+    pub output: String,
+    pub config: selfplay::Config,
+}
+
+impl SelfPlayOptions {
+    pub fn parse(args: &[String]) -> Result<SelfPlayOptions, String> {
+        let mut board = BoardOptions::default();
+        let mut engine = EngineOptions::default();
+        let mut games = 100;
+        let mut output = None;
+        let mut random_plies = 2;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--size" | "--blocks" | "--seed" => { board.parse_flag(flag, &mut args)?; },
+                "--games" => games = value(&mut args, flag)?,
+                "--output" => output = Some(value(&mut args, flag)?),
+                "--random-plies" => random_plies = value(&mut args, flag)?,
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        let output = output.ok_or_else(|| String::from("--output: required"))?;
+        board.board()?;
+        let config = selfplay::Config { size: board.size, blocks: board.blocks, random_plies };
+        Ok(SelfPlayOptions { board, engine, games, output, config })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServeOptions {
+    pub engine: EngineOptions,
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServeOptions {
+    pub fn parse(args: &[String]) -> Result<ServeOptions, String> {
+        let mut opts = ServeOptions {
+            engine: EngineOptions::default(),
+            host: String::from("127.0.0.1"),
+            port: 8080,
+        };
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if opts.engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--host" => opts.host = value(&mut args, flag)?,
+                "--port" => opts.port = value(&mut args, flag)?,
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        Ok(opts)
+    }
+
+    pub fn addr(&self) -> String { format!("{}:{}", self.host, self.port) }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainOptions {
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub epochs: usize,
+    pub rate: f64,
+}
+
+impl TrainOptions {
+    pub fn parse(args: &[String]) -> Result<TrainOptions, String> {
+        let mut inputs = Vec::new();
+        let mut output = None;
+        let mut epochs = 200;
+        let mut rate: f64 = 0.5;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--input" => inputs.push(value(&mut args, flag)?),
+                "--output" => output = Some(value(&mut args, flag)?),
+                "--epochs" => epochs = value(&mut args, flag)?,
+                "--rate" => {
+                    rate = value(&mut args, flag)?;
+                    if rate.is_nan() || rate <= 0.0 {
+                        return Err(String::from("--rate: must be positive"));
+                    }
+                },
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        if inputs.is_empty() { return Err(String::from("--input: required")); }
+        let output = output.ok_or_else(|| String::from("--output: required"))?;
+        Ok(TrainOptions { inputs, output, epochs, rate })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookOptions {
+    pub board: BoardOptions,
+    pub engine: EngineOptions,
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub plies: usize,
+    pub width: usize,
+}
+
+impl BookOptions {
+    pub fn parse(args: &[String]) -> Result<BookOptions, String> {
+        let mut board = BoardOptions::default();
+        let mut engine = EngineOptions::default();
+        let mut inputs = Vec::new();
+        let mut output = None;
+        let mut plies = 4;
+        let mut width = 3;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if board.parse_flag(flag, &mut args)? { continue; }
+            if engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--input" => inputs.push(value(&mut args, flag)?),
+                "--output" => output = Some(value(&mut args, flag)?),
+                "--plies" => plies = value(&mut args, flag)?,
+                "--width" => width = value(&mut args, flag)?,
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        let output = output.ok_or_else(|| String::from("--output: required"))?;
+        Ok(BookOptions { board, engine, inputs, output, plies, width })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleOptions {
+    pub board: BoardOptions,
+    pub engine: EngineOptions,
+    pub count: usize,
+    pub games: usize,
+    pub selfplay: bool,
+    pub output: Option<String>,
+    pub check: Option<String>,
+    pub config: puzzle::Config,
+}
+
+impl PuzzleOptions {
+    pub fn parse(args: &[String]) -> Result<PuzzleOptions, String> {
+        let mut board = BoardOptions { size: 7, blocks: 4, ..BoardOptions::default() };
+        let mut engine = EngineOptions::default();
+        let mut count = 10;
+        let mut games = 1000;
+        let mut depth = 2;
+        let mut selfplay = false;
+        let mut output = None;
+        let mut check = None;
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            if engine.parse_flag(flag, &mut args)? { continue; }
+            match flag.as_str() {
+                "--size" | "--blocks" | "--seed" => { board.parse_flag(flag, &mut args)?; },
+                "--count" => count = value(&mut args, flag)?,
+                "--games" => games = value(&mut args, flag)?,
+                "--depth" => {
+                    depth = value(&mut args, flag)?;
+                    if depth == 0 { return Err(String::from("--depth: must be positive")); }
+                },
+                "--selfplay" => selfplay = true,
+                "--output" => output = Some(value(&mut args, flag)?),
+                "--check" => check = Some(value(&mut args, flag)?),
+                _ => return Err(format!("unknown option `{}`", flag)),
+            }
+        }
+        board.board()?;
+        let config = puzzle::Config { size: board.size, blocks: board.blocks, depth };
+        Ok(PuzzleOptions { board, engine, count, games, selfplay, output, check, config })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn play_options_parse() {
+        assert_eq!(Ok(PlayOptions::default()), PlayOptions::parse(&[]));
+        let opts = PlayOptions::parse(&args(
+            "--size 8 --block 1,2 --block 3,4 --p1 random --p2 alphabeta --first 2 --playouts 100"
+        )).unwrap();
+        assert_eq!(8, opts.board.size);
+        assert_eq!(vec![(1, 2), (3, 4)], opts.board.layout);
+        assert_eq!([PlayerKind::Random, PlayerKind::AlphaBeta], opts.seats);
+        assert_eq!(Budget::Iterations(100), opts.engine.budget);
+        assert_eq!("AlphaBeta", opts.ordered_seats()[0].player(&opts.engine).unwrap().name());
+        let b = opts.board.board().unwrap();
+        assert_eq!(Some(Entry::Block), b.get(3, 4));
+        assert!(!opts.tui);
+        assert!(PlayOptions::parse(&args("--tui")).unwrap().tui);
+        let opts = PlayOptions::parse(&args("--connect 10.0.0.1:7070")).unwrap();
+        assert_eq!(Some(Network::Connect(String::from("10.0.0.1:7070"))), opts.network);
+        let opts = PlayOptions::parse(&args("--ascii --color --labels")).unwrap();
+        assert_eq!(Style { colour: true, labels: true, ..Style::ascii() }, opts.style);
+    }
+
+    #[test]
+    fn play_options_errors() {
+        assert!(PlayOptions::parse(&args("--size")).is_err());
+        assert!(PlayOptions::parse(&args("--size x")).is_err());
+        assert!(PlayOptions::parse(&args("--p1 robot")).is_err());
+        let opts = PlayOptions::parse(&args("--p1 ext:/nonexistent/engine")).unwrap();
+        assert!(opts.seats[0].player(&opts.engine).is_err());
+        assert!(PlayOptions::parse(&args("--first 3")).is_err());
+        assert!(PlayOptions::parse(&args("--block 1")).is_err());
+        assert!(PlayOptions::parse(&args("--frobnicate")).is_err());
+        assert!(PlayOptions::parse(&args("--policy /nonexistent/policy")).is_err());
+        assert!(PlayOptions::parse(&args("--network /nonexistent/network")).is_err());
+        assert!(PlayOptions::parse(&args("--network-weight 1.5")).is_err());
+        assert!(PlayOptions::parse(&args("--network-weight x")).is_err());
+        let opts = PlayOptions::parse(&args("--size 4 --block 4,0")).unwrap();
+        assert!(opts.board.board().is_err());
+        let opts = PlayOptions::parse(&args("--size 4 --blocks 16")).unwrap();
+        assert!(opts.board.board().is_err());
+        let edges = "0,0 0,1 0,2 0,3 1,0 1,3 2,0 2,3 3,0 3,1 3,2 3,3";
+        let flags = edges.split(' ').map(|cell| format!("--block {}", cell)).collect::<Vec<_>>();
+        let opts = PlayOptions::parse(&args(&format!("--size 4 {}", flags.join(" ")))).unwrap();
+        assert!(opts.board.board().is_err());
+    }
+
+    #[test]
+    fn tournament_options_parse() {
+        let opts = TournamentOptions::parse(&args(
+            "--player mcts --player random --size 6 --pairs 10 --sprt -5,5 --playouts 50"
+        )).unwrap();
+        assert_eq!(vec![PlayerKind::Mcts, PlayerKind::Random], opts.entrants);
+        assert_eq!(Config { size: 6, blocks: 6, pairs: 10, sprt: Some(Sprt::new(-5.0, 5.0)) },
+                   opts.config);
+        assert!(TournamentOptions::parse(&args("--player mcts")).is_err());
+        assert!(TournamentOptions::parse(&args("--player mcts --player human")).is_err());
+        assert!(TournamentOptions::parse(&args(
+            "--player mcts --player random --sprt 5,0"
+        )).is_err());
+        assert!(TournamentOptions::parse(&args(
+            "--player mcts --player random --player random --sprt 0,5"
+        )).is_err());
+        assert!(TournamentOptions::parse(&args("--player mcts --player random --size 3")).is_err());
+    }
+
+    #[test]
+    fn self_play_options_parse() {
+        let opts = SelfPlayOptions::parse(&args("--output s.bin --size 5 --random-plies 0"))
+            .unwrap();
+        assert_eq!(selfplay::Config { size: 5, blocks: 6, random_plies: 0 }, opts.config);
+        assert!(SelfPlayOptions::parse(&args("--size 5")).is_err());
+        assert!(SelfPlayOptions::parse(&args("--output s.bin --size 4 --blocks 16")).is_err());
+    }
+
+    #[test]
+    fn train_options_parse() {
+        let opts = TrainOptions::parse(&args(
+            "--input a.bin --input b.bin --output p.txt --epochs 10"
+        )).unwrap();
+        assert_eq!(vec![String::from("a.bin"), String::from("b.bin")], opts.inputs);
+        assert_eq!(10, opts.epochs);
+        assert!(TrainOptions::parse(&args("--output p.txt")).is_err());
+        assert!(TrainOptions::parse(&args("--input a.bin")).is_err());
+        assert!(TrainOptions::parse(&args("--input a.bin --output p.txt --rate 0")).is_err());
+    }
+
+    #[test]
+    fn serve_options_parse() {
+        let opts = ServeOptions::parse(&args("--port 0 --playouts 100")).unwrap();
+        assert_eq!("127.0.0.1:0", opts.addr());
+        assert_eq!(Budget::Iterations(100), opts.engine.budget);
+        assert!(ServeOptions::parse(&args("--port 70000")).is_err());
+    }
+
+    #[test]
+    fn book_options_parse() {
+        let opts = BookOptions::parse(&args("--seed 3 --output b.txt --plies 2 --playouts 10"))
+            .unwrap();
+        assert_eq!((2, 3), (opts.plies, opts.width));
+        assert!(opts.inputs.is_empty());
+        assert!(BookOptions::parse(&args("--plies 2")).is_err());
+        assert!(PlayOptions::parse(&args("--book /nonexistent/book")).is_err());
+    }
+
+    #[test]
+    fn puzzle_options_parse() {
+        let opts = PuzzleOptions::parse(&args("--depth 3 --count 5 --size 6 --selfplay")).unwrap();
+        assert_eq!(puzzle::Config { size: 6, blocks: 4, depth: 3 }, opts.config);
+        assert_eq!((5, true, None), (opts.count, opts.selfplay, opts.check));
+        assert!(PuzzleOptions::parse(&args("--depth 0")).is_err());
+        assert!(PuzzleOptions::parse(&args("--size 2")).is_err());
+    }
+
+    #[test]
+    fn board_options_seed() {
+        let opts = PlayOptions::parse(&args("--seed 7 --blocks 10")).unwrap();
+        let b1 = opts.board.board().unwrap();
+        let b2 = opts.board.board().unwrap();
+        assert_eq!(b1.to_notation(), b2.to_notation());
+    }
+}
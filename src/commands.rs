@@ -0,0 +1,295 @@
+// The subcommands, each taking the arguments after its name.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::TcpListener;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use zgc4::board::{Entry, Outcome};
+use zgc4::book::Book;
+use zgc4::game::{Event, Game};
+use zgc4::player::{NetworkError, NetworkPlayer, Player, PlayerError};
+use zgc4::policy::Trainer;
+use zgc4::protocol::Engine;
+use zgc4::puzzle;
+use zgc4::selfplay;
+use zgc4::server::{self, Server};
+use zgc4::tournament::{self, Hypothesis, Match};
+use zgc4::tui::{RawMode, Screen, TuiPlayer};
+
+use cli::{BookOptions, EngineOptions, Network, PlayOptions, PlayerKind, PuzzleOptions,
+          SelfPlayOptions, ServeOptions, TournamentOptions, TrainOptions};
+
+type MakePlayer<'a> = &'a dyn Fn(&PlayerKind) -> Result<Box<dyn Player>, String>;
+
+// Seats the players, with a network peer in place of the second seat when
+// listening or connecting.
+fn new_game(opts: &PlayOptions, make: MakePlayer) -> Result<Game, String> {
+    let at = |addr: &str, e: NetworkError| {
+        format!("{}: {}", addr, e.to_string().trim_start_matches("Error: "))
+    };
+    let (b, local, remote) = match opts.network {
+        None => {
+            let [a, b] = opts.ordered_seats();
+            return Ok(Game::new(opts.board.board()?, [make(a)?, make(b)?]));
+        },
+        Some(Network::Listen(ref addr)) => {
+            let b = opts.board.board()?;
+            let local = make(&opts.seats[0])?;
+            let entry = if opts.first == 1 { Entry::Player2 } else { Entry::Player1 };
+            println!("Waiting for a peer on {}", addr);
+            let remote = NetworkPlayer::listen(addr, &b, entry, &local.name())
+                .map_err(|e| at(addr, e))?;
+            (b, local, remote)
+        },
+        Some(Network::Connect(ref addr)) => {
+            let local = make(&opts.seats[0])?;
+            let (remote, b) = NetworkPlayer::connect(addr, &local.name())
+                .map_err(|e| at(addr, e))?;
+            (b, local, remote)
+        },
+    };
+    println!("Playing {} against {}", local.name(), remote.name());
+    Ok(Game::new(b, remote.seat(local)))
+}
+
+pub fn play(args: &[String]) -> Result<(), String> {
+    let opts = PlayOptions::parse(args)?;
+    if opts.tui { return play_tui(&opts); }
+    let mut game = new_game(&opts, &|kind| kind.player(&opts.engine))?;
+    println!("{}", game.board().styled(opts.style));
+    while let Some(event) = game.step() {
+        let b = game.board();
+        match event {
+            Event::Moved(entry, m) => {
+                if let Some(status) = game.player(entry).and_then(|p| p.status()) {
+                    println!("{}", status);
+                }
+                println!("{}", b.styled(opts.style.with_last(Some(m))));
+            },
+            Event::Undone(_, 0) => println!("Nothing to undo."),
+            Event::Undone(..) => println!("{}", b.styled(opts.style)),
+            Event::Resigned(entry) => println!("{} resigns.", entry),
+            Event::DrawOffered(entry, true) => println!("{} offers a draw, accepted.", entry),
+            Event::DrawOffered(entry, false) => println!("{} offers a draw, declined.", entry),
+            Event::Aborted(..) => println!("Aborted."),
+            Event::Finished(..) => (),
+        }
+    }
+    match game.result() {
+        Some(Outcome::Draw) => println!("Drawn."),
+        Some(Outcome::Win(entry)) => println!("Won! ({})", entry),
+        None => (),
+    }
+    match game.error() {
+        None | Some(PlayerError::Aborted) => (),
+        Some(e) => eprintln!("{}", e),
+    }
+    println!("{}", game.record());
+    Ok(())
+}
+
+fn play_tui(opts: &PlayOptions) -> Result<(), String> {
+    let screen = Rc::new(RefCell::new(Screen::new(Box::new(io::stdout()))));
+    let tty = || File::open("/dev/tty").map_err(|e| format!("/dev/tty: {}", e));
+    let mut game = new_game(opts, &|kind| match *kind {
+        PlayerKind::Human => {
+            let mut p = TuiPlayer::new(screen.clone(), Box::new(tty()?));
+            p.set_hint_budget(opts.engine.budget);
+            Ok(Box::new(p))
+        },
+        ref kind => kind.player(&opts.engine),
+    })?;
+    let name = |entry| game.player(entry).map(|p| p.name()).unwrap_or_default();
+    let names = [name(Entry::Player1), name(Entry::Player2)];
+    screen.borrow_mut().set_names(names);
+    {
+        let _raw = RawMode::enter().map_err(|e| format!("terminal: {}", e))?;
+        while let Some(event) = game.step() {
+            let mut screen = screen.borrow_mut();
+            match event {
+                Event::Moved(entry, m) => {
+                    if let Some(status) = game.player(entry).and_then(|p| p.status()) {
+                        screen.set_status(entry, status);
+                    }
+                    screen.set_last(Some(m));
+                    screen.set_message("");
+                },
+                Event::Undone(_, 0) => screen.set_message("Nothing to undo."),
+                Event::Undone(..) => {
+                    screen.set_last(None);
+                    screen.set_message("Move taken back.");
+                },
+                Event::Resigned(entry) => screen.set_message(&format!("{} resigns.", entry)),
+                Event::DrawOffered(entry, accepted) => {
+                    let answer = if accepted { "accepted" } else { "declined" };
+                    screen.set_message(&format!("{} offers a draw, {}.", entry, answer));
+                },
+                Event::Aborted(..) => break,
+                Event::Finished(..) => (),
+            }
+            if !game.is_over() { screen.draw(game.board(), None).map_err(|e| e.to_string())?; }
+        }
+        if game.result().is_some() {
+            let mut screen = screen.borrow_mut();
+            let message = match game.result() {
+                Some(Outcome::Win(entry)) => format!("Won! ({})  Press any key.", entry),
+                _ => String::from("Drawn.  Press any key."),
+            };
+            screen.set_message(&message);
+            screen.draw(game.board(), None).map_err(|e| e.to_string())?;
+            let _ = zgc4::tui::read_key(&mut tty()?);
+        }
+    }
+    println!("{}", game.board().styled(opts.style));
+    match game.error() {
+        None | Some(PlayerError::Aborted) => (),
+        Some(e) => eprintln!("{}", e),
+    }
+    println!("{}", game.record());
+    Ok(())
+}
+
+pub fn protocol(args: &[String]) -> Result<(), String> {
+    let mut opts = EngineOptions::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if !opts.parse_flag(flag, &mut args)? {
+            return Err(format!("unknown option `{}`", flag));
+        }
+    }
+    let mut engine = Engine::new(opts.mcts());
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    engine.run(stdin.lock(), stdout.lock()).map_err(|e| e.to_string())
+}
+
+pub fn tournament(args: &[String]) -> Result<(), String> {
+    let opts = TournamentOptions::parse(args)?;
+    let mut rng = opts.board.rng();
+    let mut players = opts.players()?;
+    let names = players.iter().map(|p| p.name()).collect::<Vec<_>>();
+    if players.len() == 2 {
+        let b = players.pop().unwrap();
+        let a = players.pop().unwrap();
+        let mut m = Match::new(a, b, opts.config);
+        let score = m.run(&mut rng, |score| {
+            println!("{} vs {}: {} after {} games", names[0], names[1], score, score.games());
+        }).map_err(|e| e.to_string().trim_start_matches("Error: ").to_string())?;
+        if let Some(elo) = score.elo() { println!("Elo difference: {}", elo); }
+        if let Some(sprt) = opts.config.sprt {
+            let (lower, upper) = sprt.bounds();
+            let llr = sprt.llr(&score);
+            let decision = match sprt.decision(&score) {
+                Some(Hypothesis::H0) => "H0 accepted",
+                Some(Hypothesis::H1) => "H1 accepted",
+                None => "inconclusive",
+            };
+            println!("SPRT: LLR {:.2} ({:.2}, {:.2}): {}", llr, lower, upper, decision);
+        }
+    } else {
+        let (_, table) = tournament::round_robin(players, opts.config, &mut rng, |i, j, score| {
+            println!("{} vs {}: {} after {} games", names[i], names[j], score, score.games());
+        }).map_err(|e| e.to_string().trim_start_matches("Error: ").to_string())?;
+        for (i, scores) in table.iter().enumerate() {
+            let total = scores.iter().fold(0.0, |sum, score| sum + score.points());
+            let ngames = scores.iter().fold(0, |sum, score| sum + score.games());
+            println!("{:>2}. {:<12} {:>6.1} / {}", i + 1, names[i], total, ngames);
+        }
+    }
+    Ok(())
+}
+
+pub fn selfplay(args: &[String]) -> Result<(), String> {
+    let opts = SelfPlayOptions::parse(args)?;
+    let mut rng = opts.board.rng();
+    let player = opts.engine.mcts();
+    let file = File::create(&opts.output).map_err(|e| format!("{}: {}", opts.output, e))?;
+    let mut w = BufWriter::new(file);
+    let io_error = |e: io::Error| format!("{}: {}", opts.output, e);
+    selfplay::write_header(&mut w).map_err(io_error)?;
+    let mut nsamples = 0;
+    for i in 0..opts.games {
+        let samples = selfplay::play_game(&player, &opts.config, &mut rng);
+        for sample in &samples { sample.write(&mut w).map_err(io_error)?; }
+        nsamples += samples.len();
+        println!("Game {}/{}: {} samples ({} total)", i + 1, opts.games, samples.len(), nsamples);
+    }
+    w.flush().map_err(io_error)
+}
+
+pub fn train(args: &[String]) -> Result<(), String> {
+    let opts = TrainOptions::parse(args)?;
+    let mut samples = Vec::new();
+    for path in &opts.inputs {
+        samples.extend(selfplay::load(path).map_err(|e| format!("{}: {}", path, e))?);
+    }
+    println!("{} samples", samples.len());
+    let trainer = Trainer { epochs: opts.epochs, rate: opts.rate, ..Trainer::new() };
+    let policy = trainer.train(&samples, |epoch, loss| {
+        if epoch % 10 == 0 { println!("Epoch {}: loss {:.4}", epoch, loss); }
+    });
+    println!("Final loss {:.4}", Trainer::loss(&policy, &samples));
+    print!("{}", policy);
+    policy.save(&opts.output).map_err(|e| format!("{}: {}", opts.output, e))
+}
+
+pub fn serve(args: &[String]) -> Result<(), String> {
+    let opts = ServeOptions::parse(args)?;
+    let listener = TcpListener::bind(opts.addr()).map_err(|e| format!("{}: {}", opts.addr(), e))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let server = Server::new(opts.engine.mcts());
+    println!("Listening on http://{}", addr);
+    io::stdout().flush().map_err(|e| e.to_string())?;
+    server::serve(Arc::new(server), listener).map_err(|e| e.to_string())
+}
+
+pub fn book(args: &[String]) -> Result<(), String> {
+    let opts = BookOptions::parse(args)?;
+    let mut book = Book::new();
+    if opts.inputs.is_empty() {
+        let b = opts.board.board()?;
+        println!("{}", b);
+        book.explore(&opts.engine.mcts(), &b, opts.plies, opts.width);
+    }
+    for path in &opts.inputs {
+        for sample in selfplay::load(path).map_err(|e| format!("{}: {}", path, e))? {
+            book.add_sample(&sample, opts.plies);
+        }
+    }
+    println!("{} positions", book.len());
+    book.save(&opts.output).map_err(|e| format!("{}: {}", opts.output, e))
+}
+
+pub fn puzzles(args: &[String]) -> Result<(), String> {
+    let opts = PuzzleOptions::parse(args)?;
+    let player = opts.engine.mcts();
+    if let Some(ref path) = opts.check {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let puzzles = puzzle::read(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?;
+        let mut solved = 0;
+        for puzzle in &puzzles {
+            let info = player.search(&puzzle.board);
+            let ok = puzzle.is_solved_by(info.m);
+            println!("{} {} ({})", puzzle, if ok { "solved" } else { "missed" }, info.m);
+            if ok { solved += 1; }
+        }
+        println!("Solved {}/{}", solved, puzzles.len());
+        return Ok(());
+    }
+    let mut rng = opts.board.rng();
+    let selfplay_player = if opts.selfplay { Some(&player) } else { None };
+    let found = puzzle::generate(selfplay_player, &opts.config, opts.games, opts.count, &mut rng);
+    let text = found.iter().map(|p| format!("{}\n", p)).collect::<String>();
+    match opts.output {
+        Some(ref path) => {
+            File::create(path).and_then(|mut f| f.write_all(text.as_bytes()))
+                .map_err(|e| format!("{}: {}", path, e))?;
+            println!("{} puzzles", found.len());
+        },
+        None => print!("{}", text),
+    }
+    Ok(())
+}
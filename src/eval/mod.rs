@@ -0,0 +1,20 @@
+mod nn;
+
+use board::Board;
+
+// A static estimate of a position, from the point of view of the side to
+// move. `priors` holds one probability per legal move, in
+// `legal_moves_iter` order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub priors: Vec<f64>,
+}
+
+pub trait Evaluator: Send + Sync {
+    // Returns `None` for positions the evaluator cannot handle, such as
+    // boards of an unsupported size.
+    fn evaluate(&self, b: &Board) -> Option<Estimate>;
+}
+
+pub use self::nn::Network;
@@ -0,0 +1,229 @@
+// A small fully-connected network for one board size N. The input is four
+// N×N planes (own stones, opponent stones, blocks, empty cells); a single
+// ReLU hidden layer feeds a sigmoid value head and a policy head with one
+// logit per edge entry point, indexed by `MoveId`.
+//
+// Weights are stored as `MAGIC`, then N and the hidden layer width as u32,
+// then f32 values for W1, b1, Wv, bv, Wp and bp in that order, with matrices
+// row-major and everything little-endian.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use board::{Board, Entry, MAX_SIZE};
+use eval::{Estimate, Evaluator};
+
+pub const MAGIC: &[u8; 8] = b"ZGC4NN01";
+
+// The most weights `read` accepts, so a corrupt header cannot make it
+// allocate gigabytes; a 32×32 board with 4096 hidden units needs about 17M.
+const MAX_WEIGHTS: usize = 1 << 25;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    size: usize,
+    hidden: usize,
+    w1: Vec<f32>,
+    b1: Vec<f32>,
+    wv: Vec<f32>,
+    bv: f32,
+    wp: Vec<f32>,
+    bp: Vec<f32>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f32s<R: Read>(r: &mut R, n: usize) -> io::Result<Vec<f32>> {
+    let mut buf = vec![0u8; n * 4];
+    r.read_exact(&mut buf)?;
+    Ok(buf.chunks(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+fn write_f32s<W: Write>(w: &mut W, xs: &[f32]) -> io::Result<()> {
+    for x in xs { w.write_all(&x.to_le_bytes())?; }
+    Ok(())
+}
+
+// y = W x + b, for W stored row-major with one row per output.
+fn affine(w: &[f32], b: &[f32], x: &[f32]) -> Vec<f32> {
+    w.chunks(x.len()).zip(b).map(|(row, &b)| {
+        row.iter().zip(x).fold(b, |sum, (&w, &x)| sum + w * x)
+    }).collect()
+}
+
+impl Network {
+    pub fn random<R: Rng>(rng: &mut R, size: usize, hidden: usize) -> Network {
+        let ninput = 4 * size * size;
+        let noutput = 4 * size;
+        let mut init = |n: usize, fan_in: usize| {
+            let scale = (1.0 / fan_in as f32).sqrt();
+            (0..n).map(|_| (rng.gen::<f32>() * 2.0 - 1.0) * scale).collect::<Vec<_>>()
+        };
+        let w1 = init(hidden * ninput, ninput);
+        let wv = init(hidden, hidden);
+        let wp = init(noutput * hidden, hidden);
+        Network {
+            size, hidden, w1, wv, wp,
+            b1: vec![0.0; hidden],
+            bv: 0.0,
+            bp: vec![0.0; noutput],
+        }
+    }
+
+    pub fn size(&self) -> usize { self.size }
+
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Network> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC { return Err(invalid("not a network weights file")); }
+        let size = read_u32(r)? as usize;
+        let hidden = read_u32(r)? as usize;
+        if size == 0 || size > MAX_SIZE || hidden == 0 || hidden > 1 << 16 {
+            return Err(invalid("invalid network dimensions"));
+        }
+        let ninput = 4 * size * size;
+        let noutput = 4 * size;
+        if (ninput + noutput + 2) * hidden + noutput + 1 > MAX_WEIGHTS {
+            return Err(invalid("network is too large"));
+        }
+        let w1 = read_f32s(r, hidden * ninput)?;
+        let b1 = read_f32s(r, hidden)?;
+        let wv = read_f32s(r, hidden)?;
+        let bv = read_f32s(r, 1)?[0];
+        let wp = read_f32s(r, noutput * hidden)?;
+        let bp = read_f32s(r, noutput)?;
+        Ok(Network { size, hidden, w1, b1, wv, bv, wp, bp })
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&(self.size as u32).to_le_bytes())?;
+        w.write_all(&(self.hidden as u32).to_le_bytes())?;
+        write_f32s(w, &self.w1)?;
+        write_f32s(w, &self.b1)?;
+        write_f32s(w, &self.wv)?;
+        write_f32s(w, &[self.bv])?;
+        write_f32s(w, &self.wp)?;
+        write_f32s(w, &self.bp)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Network> {
+        Network::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()
+    }
+
+    fn planes(&self, b: &Board) -> Vec<f32> {
+        let n = self.size * self.size;
+        let own = b.active();
+        let mut x = vec![0.0; 4 * n];
+        for (i, &e) in b.entries().iter().enumerate() {
+            let plane = match e {
+                _ if e == own => 0,
+                Entry::Player1 | Entry::Player2 => 1,
+                Entry::Block => 2,
+                Entry::Empty => 3,
+            };
+            x[plane * n + i] = 1.0;
+        }
+        x
+    }
+}
+
+impl Evaluator for Network {
+    fn evaluate(&self, b: &Board) -> Option<Estimate> {
+        if b.size() != self.size { return None; }
+        let mut h = affine(&self.w1, &self.b1, &self.planes(b));
+        for x in &mut h { *x = x.max(0.0); }
+        let v = self.wv.iter().zip(&h).fold(self.bv, |sum, (&w, &x)| sum + w * x);
+        let value = 1.0 / (1.0 + (-f64::from(v)).exp());
+        let logits = affine(&self.wp, &self.bp, &h);
+        let legal = b.legal_moves_iter().map(|m| f64::from(logits[m.id().index()]))
+            .collect::<Vec<_>>();
+        let max = legal.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exps = legal.iter().map(|&l| (l - max).exp()).collect::<Vec<_>>();
+        let total = exps.iter().sum::<f64>();
+        let priors = exps.into_iter().map(|e| e / total).collect();
+        Some(Estimate { value, priors })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+    use player::{Budget, MCTSPlayer};
+
+    // Puts all of the prior on the first legal move.
+    struct Favourite;
+
+    impl Evaluator for Favourite {
+        fn evaluate(&self, b: &Board) -> Option<Estimate> {
+            let mut priors = vec![0.0; b.legal_moves_iter().count()];
+            priors[0] = 1.0;
+            Some(Estimate { value: 0.5, priors })
+        }
+    }
+
+    #[test]
+    fn network_round_trip() {
+        let net = Network::random(&mut rand::thread_rng(), 5, 8);
+        let mut buf = Vec::new();
+        net.write(&mut buf).unwrap();
+        assert_eq!(8 + 8 + 4 * (8 * 100 + 8 + 8 + 1 + 20 * 8 + 20), buf.len());
+        assert_eq!(net, Network::read(&mut &buf[..]).unwrap());
+        assert!(Network::read(&mut &buf[..buf.len() - 1]).is_err());
+        assert!(Network::read(&mut &b"ZGC4NN02"[..]).is_err());
+        let mut huge = MAGIC.to_vec();
+        huge.extend_from_slice(&255u32.to_le_bytes());
+        huge.extend_from_slice(&(1u32 << 16).to_le_bytes());
+        let err = Network::read(&mut &huge[..]).unwrap_err();
+        assert_eq!("network is too large", err.to_string());
+    }
+
+    #[test]
+    fn network_evaluate() {
+        let net = Network::random(&mut rand::thread_rng(), 5, 8);
+        let b = Board::generate(5, 3);
+        let estimate = net.evaluate(&b).unwrap();
+        assert!(estimate.value > 0.0 && estimate.value < 1.0);
+        assert_eq!(b.legal_moves_iter().count(), estimate.priors.len());
+        assert!((estimate.priors.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(None, net.evaluate(&Board::new(6)));
+    }
+
+    #[test]
+    fn network_as_mcts_evaluator() {
+        let mut player = MCTSPlayer::with_budget(Budget::Iterations(100));
+        player.set_evaluator(Box::new(Network::random(&mut rand::thread_rng(), 5, 8)), 1.0);
+        let b = Board::generate(5, 3);
+        let info = player.search(&b);
+        assert!(b.legal_moves_iter().any(|m| m == info.m));
+    }
+
+    #[test]
+    fn mcts_follows_priors() {
+        let mut player = MCTSPlayer::with_budget(Budget::Iterations(50));
+        player.set_evaluator(Box::new(Favourite), 1.0);
+        let b = Board::new(10);
+        let (_, children) = player.analyse(&b);
+        assert_eq!(b.legal_moves_iter().next(), Some(children[0].m));
+        assert!(children[0].visits > 5, "{:?}", children[0]);
+        assert!(children.iter().filter(|c| c.visits > 0).count() < 10);
+    }
+}
@@ -0,0 +1,299 @@
+use board::{Board, Entry, GameState, LegalMove, Outcome};
+use player::{Decision, Player, PlayerError};
+use record::Record;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Moved(Entry, LegalMove),
+    // The player asked to take back moves; this many plies were undone.
+    Undone(Entry, usize),
+    Resigned(Entry),
+    DrawOffered(Entry, bool),
+    Aborted(Entry),
+    Finished(Outcome),
+}
+
+pub struct Game {
+    initial: Board,
+    board: Board,
+    players: [Box<dyn Player>; 2],
+    history: Vec<LegalMove>,
+    // What the player reported about each move in `history` when making it.
+    info: Vec<Option<String>>,
+    started: bool,
+    // Set when the game ends other than on the board.
+    agreed: Option<Outcome>,
+    error: Option<PlayerError>,
+}
+
+impl Game {
+    pub fn new(board: Board, players: [Box<dyn Player>; 2]) -> Game {
+        let initial = board.clone();
+        Game {
+            initial,
+            board,
+            players,
+            history: Vec::new(),
+            info: Vec::new(),
+            started: false,
+            agreed: None,
+            error: None,
+        }
+    }
+
+    pub fn into_players(self) -> [Box<dyn Player>; 2] { self.players }
+
+    pub fn board(&self) -> &Board { &self.board }
+
+    pub fn history(&self) -> &[LegalMove] { &self.history }
+
+    pub fn result(&self) -> Option<Outcome> {
+        self.agreed.or_else(|| self.board.outcome())
+    }
+
+    pub fn winner(&self) -> Option<Entry> {
+        match self.result() {
+            Some(Outcome::Win(entry)) => Some(entry),
+            _ => None,
+        }
+    }
+
+    // Why the game was aborted, if it was.
+    pub fn error(&self) -> Option<&PlayerError> { self.error.as_ref() }
+
+    pub fn take_error(&mut self) -> Option<PlayerError> { self.error.take() }
+
+    pub fn is_over(&self) -> bool {
+        self.error.is_some() || self.result().is_some()
+    }
+
+    pub fn player(&self, entry: Entry) -> Option<&dyn Player> {
+        entry.seat().map(|i| &*self.players[i])
+    }
+
+    fn player_mut(&mut self, entry: Entry) -> Option<&mut dyn Player> {
+        match entry.seat() {
+            Some(i) => Some(&mut *self.players[i]),
+            None => None,
+        }
+    }
+
+    pub fn step(&mut self) -> Option<Event> {
+        if self.is_over() || self.board.state() != GameState::Ongoing { return None; }
+        if !self.started {
+            self.started = true;
+            self.reset_players();
+        }
+        let active = self.board.active();
+        let b = self.board.clone();
+        let (choice, info) = match self.player_mut(active) {
+            Some(p) => (p.choose(&b), p.status()),
+            None => return None,
+        };
+        let event = match choice {
+            Ok(Decision::Move(m)) => {
+                self.board.make_legal_move(m);
+                self.history.push(m);
+                self.info.push(info);
+                let b = self.board.clone();
+                if let Some(p) = self.player_mut(active.flip()) { p.opponent_moved(&b, m); }
+                Event::Moved(active, m)
+            },
+            Ok(Decision::Undo) => {
+                let n = self.undo();
+                if n > 0 { self.reset_players(); }
+                Event::Undone(active, n)
+            },
+            Ok(Decision::Resign) => {
+                self.agreed = Some(Outcome::Win(active.flip()));
+                Event::Resigned(active)
+            },
+            Ok(Decision::OfferDraw) => {
+                let accepted = self.player_mut(active.flip()).is_some_and(|p| p.accept_draw(&b));
+                if accepted { self.agreed = Some(Outcome::Draw); }
+                Event::DrawOffered(active, accepted)
+            },
+            Err(e) => {
+                self.error = Some(e);
+                Event::Aborted(active)
+            },
+        };
+        if self.is_over() {
+            let (b, result) = (self.board.clone(), self.result());
+            for p in &mut self.players { p.game_over(&b, result); }
+        }
+        Some(event)
+    }
+
+    fn reset_players(&mut self) {
+        let b = self.board.clone();
+        for p in &mut self.players { p.new_game(&b); }
+    }
+
+    // Takes back moves up to and including the active player's last one,
+    // returning how many plies were undone.
+    fn undo(&mut self) -> usize {
+        let parity = if self.board.active() == self.initial.active() { 0 } else { 1 };
+        let keep = match (0..self.history.len()).rev().find(|&i| i & 1 == parity) {
+            Some(i) => i,
+            None => return 0,
+        };
+        let n = self.history.len() - keep;
+        self.history.truncate(keep);
+        self.info.truncate(keep);
+        self.board = self.initial.clone();
+        for &m in &self.history { self.board.make_legal_move(m); }
+        n
+    }
+
+    // Returns `None` if a player aborted the game.
+    pub fn run<F: FnMut(&Board, Event)>(&mut self, mut on_event: F) -> Option<Outcome> {
+        while let Some(event) = self.step() {
+            on_event(&self.board, event);
+        }
+        let outcome = self.result();
+        if let Some(outcome) = outcome { on_event(&self.board, Event::Finished(outcome)); }
+        outcome
+    }
+
+    pub fn record(&self) -> Record {
+        let mut game = Record::new(self.initial.clone());
+        for (&entry, p) in [Entry::Player1, Entry::Player2].iter().zip(&self.players) {
+            game.set_player(entry, &p.name());
+        }
+        for (m, info) in self.history.iter().zip(&self.info) {
+            game.push(m.base(), info.clone());
+        }
+        game.set_result(self.result());
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::{self, Cursor};
+    use std::rc::Rc;
+    use player::{Budget, HumanPlayer, MCTSPlayer, RandomPlayer};
+
+    fn scripted(input: &str) -> Box<dyn Player> {
+        Box::new(HumanPlayer::with_io(Box::new(Cursor::new(String::from(input))),
+                                      Box::new(io::sink())))
+    }
+
+    #[test]
+    fn game_run_random() {
+        let players: [Box<dyn Player>; 2] = [Box::new(RandomPlayer), Box::new(RandomPlayer)];
+        let mut game = Game::new(Board::generate(6, 4), players);
+        let mut nmoved = 0;
+        let mut finished = None;
+        let outcome = game.run(|_, event| match event {
+            Event::Moved(..) => nmoved += 1,
+            Event::Finished(outcome) => finished = Some(outcome),
+            _ => panic!("unexpected {:?}", event),
+        }).unwrap();
+        assert_eq!(Some(outcome), finished);
+        assert_eq!(Some(outcome), game.result());
+        assert_eq!(nmoved, game.history().len());
+        assert_eq!(None, game.step());
+        match outcome {
+            Outcome::Win(entry) => assert_eq!(Some(entry), game.winner()),
+            Outcome::Draw => assert_eq!(None, game.winner()),
+        }
+        let record = game.record();
+        assert_eq!(Some(outcome), record.result());
+        assert_eq!(Some("Random"), record.player(Entry::Player1));
+        assert!(game.player(Entry::Block).is_none());
+        let b = record.final_board().unwrap();
+        assert_eq!(game.board().to_notation(), b.to_notation());
+    }
+
+    #[test]
+    fn game_record_info() {
+        let mcts = MCTSPlayer::with_budget(Budget::Iterations(20));
+        let players: [Box<dyn Player>; 2] = [Box::new(mcts), Box::new(RandomPlayer)];
+        let mut game = Game::new(Board::generate(5, 2), players);
+        game.run(|_, _| ());
+        let moves = game.record().moves().to_vec();
+        assert!(moves.iter().step_by(2).all(|ply| ply.info.is_some()));
+        assert!(moves.iter().skip(1).step_by(2).all(|ply| ply.info.is_none()));
+    }
+
+    #[test]
+    fn game_undo_and_resign() {
+        let players = [scripted("undo
+n0
+undo
+resign
+"), Box::new(RandomPlayer) as Box<_>];
+        let mut game = Game::new(Board::new(6), players);
+        assert_eq!(Some(Event::Undone(Entry::Player1, 0)), game.step());
+        assert!(matches!(game.step(), Some(Event::Moved(Entry::Player1, _))));
+        game.step();
+        assert_eq!(2, game.history().len());
+        assert_eq!(Some(Event::Undone(Entry::Player1, 2)), game.step());
+        assert!(game.history().is_empty());
+        assert_eq!(Board::new(6).to_notation(), game.board().to_notation());
+        assert_eq!(Some(Event::Resigned(Entry::Player1)), game.step());
+        assert_eq!(None, game.step());
+        assert_eq!(Some(Entry::Player2), game.winner());
+        assert_eq!(Some(Outcome::Win(Entry::Player2)), game.record().result());
+    }
+
+    struct Logger(Rc<RefCell<Vec<String>>>);
+
+    impl Player for Logger {
+        fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+            RandomPlayer.choose(b)
+        }
+
+        fn new_game(&mut self, _: &Board) { self.0.borrow_mut().push(String::from("new")); }
+
+        fn opponent_moved(&mut self, _: &Board, m: LegalMove) {
+            self.0.borrow_mut().push(m.to_string());
+        }
+
+        fn game_over(&mut self, _: &Board, result: Option<Outcome>) {
+            self.0.borrow_mut().push(format!("{:?}", result));
+        }
+    }
+
+    #[test]
+    fn game_hooks() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let players = [Box::new(Logger(log.clone())) as Box<_>, Box::new(RandomPlayer) as Box<_>];
+        let mut game = Game::new(Board::generate(5, 2), players);
+        let outcome = game.run(|_, _| ());
+        let log = log.borrow();
+        assert_eq!("new", log[0]);
+        assert_eq!(format!("{:?}", outcome), *log.last().unwrap());
+        let replies = game.history().iter().skip(1).step_by(2).map(|m| m.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(&replies[..], &log[1..log.len() - 1]);
+    }
+
+    #[test]
+    fn game_draw_offer() {
+        let players = [scripted("draw\ndraw\n"), scripted("n\ny\n")];
+        let mut game = Game::new(Board::new(6), players);
+        assert_eq!(Some(Event::DrawOffered(Entry::Player1, false)), game.step());
+        assert_eq!(None, game.result());
+        assert_eq!(Some(Event::DrawOffered(Entry::Player1, true)), game.step());
+        assert_eq!(Some(Outcome::Draw), game.result());
+        assert_eq!(None, game.step());
+    }
+
+    #[test]
+    fn game_abort() {
+        let players = [scripted("n0
+"), Box::new(RandomPlayer) as Box<_>];
+        let mut game = Game::new(Board::new(6), players);
+        let mut events = Vec::new();
+        assert_eq!(None, game.run(|_, event| events.push(event)));
+        assert_eq!(Some(&Event::Aborted(Entry::Player1)), events.last());
+        assert!(game.is_over());
+        assert!(matches!(game.error(), Some(PlayerError::Aborted)));
+        assert_eq!(None, game.result());
+    }
+}
@@ -0,0 +1,18 @@
+extern crate itertools;
+extern crate num_traits;
+extern crate smallvec;
+extern crate rand;
+
+pub mod board;
+pub mod book;
+pub mod eval;
+pub mod game;
+pub mod player;
+pub mod policy;
+pub mod protocol;
+pub mod puzzle;
+pub mod record;
+pub mod selfplay;
+pub mod server;
+pub mod tournament;
+pub mod tui;
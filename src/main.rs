@@ -1,39 +1,37 @@
-extern crate itertools;
-extern crate num_traits;
-extern crate smallvec;
 extern crate rand;
+extern crate zgc4;
 
-pub mod board;
-pub mod player;
+mod cli;
+mod commands;
 
-use std::time::Duration;
-
-use board::{Board, GameState};
-use player::Player;
+use std::env;
+use std::process;
 
 fn main() {
-    let mut b = Board::generate(10, 6);
-    let dur = Duration::new(5, 0);
-    let players: [Box<Player>; 2] = [
-        Box::new(player::HumanPlayer),
-        //Box::new(player::MCTSPlayer::new(dur)),
-        Box::new(player::MCTSPlayer::new(dur)),
-    ];
-    println!("{}", b);
-    for p in players.iter().cycle() {
-        let m = p.choose(&b);
-        let r = b.make_legal_move(m);
-        println!("{}", b);
-        match r {
-            GameState::Ongoing => (),
-            GameState::Drawn => {
-                println!("Drawn.");
-                break;
-            },
-            GameState::Won => {
-                println!("Won! ({})", b.active());
-                break;
-            },
-        }
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", cli::USAGE);
+        return;
+    }
+    let (command, args) = match args.first() {
+        Some(command) if !command.starts_with('-') => (command.as_str(), &args[1..]),
+        _ => ("play", &args[..]),
+    };
+    let result = match command {
+        "play" => commands::play(args),
+        "protocol" => commands::protocol(args),
+        "tournament" => commands::tournament(args),
+        "selfplay" => commands::selfplay(args),
+        "train" => commands::train(args),
+        "serve" => commands::serve(args),
+        "book" => commands::book(args),
+        "puzzles" => commands::puzzles(args),
+        "help" => { print!("{}", cli::USAGE); Ok(()) },
+        _ => Err(format!("unknown command `{}`", command)),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        eprint!("\n{}", cli::USAGE);
+        process::exit(2);
     }
 }
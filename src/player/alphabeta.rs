@@ -0,0 +1,109 @@
+use std::time::Instant;
+
+use rand::{self, Rng};
+
+use board::{Board, GameState, LegalMove};
+use player::{Budget, Decision, Player, PlayerError};
+
+const WIN: i32 = 1_000_000;
+
+pub struct AlphaBetaPlayer {
+    budget: Budget,
+    status: Option<String>,
+}
+
+impl AlphaBetaPlayer {
+    pub fn new(budget: Budget) -> Self {
+        AlphaBetaPlayer { budget, status: None }
+    }
+
+    fn best_move(&mut self, b: &Board) -> Option<LegalMove> {
+        self.status = None;
+        let mut rng = rand::thread_rng();
+        let mut moves = b.legal_moves_iter().collect::<Vec<_>>();
+        if let Some(&m) = moves.iter().find(|m| m.is_winning()) { return Some(m); }
+        rng.shuffle(&mut moves);
+        let mut search = Search { budget: self.budget, start: Instant::now(), nodes: 0 };
+        let mut best = (0, *moves.first()?);
+        let maxdepth = b.size() * b.size();
+        for depth in 0..maxdepth {
+            match search.root(b, &moves, depth) {
+                Some(result) => best = result,
+                None => break,
+            }
+            if best.0.abs() >= WIN - maxdepth as i32 { break; }
+        }
+        self.status = Some(format!("Choosing move with score {} after {} nodes",
+                                   best.0, search.nodes));
+        Some(best.1)
+    }
+}
+
+impl Player for AlphaBetaPlayer {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        self.best_move(b).map(Decision::Move)
+            .ok_or_else(|| PlayerError::Engine(String::from("no legal moves")))
+    }
+
+    fn name(&self) -> String { String::from("AlphaBeta") }
+
+    fn status(&self) -> Option<String> { self.status.clone() }
+}
+
+struct Search {
+    budget: Budget,
+    start: Instant,
+    nodes: usize,
+}
+
+impl Search {
+    fn root(&mut self, b: &Board, moves: &[LegalMove], depth: usize) -> Option<(i32, LegalMove)> {
+        let mut alpha = -i32::MAX;
+        let mut best = *moves.first()?;
+        for &m in moves {
+            let score = self.child(b, m, depth, alpha, i32::MAX)?;
+            if score > alpha { alpha = score; best = m; }
+        }
+        Some((alpha, best))
+    }
+
+    fn child(&mut self, b: &Board, m: LegalMove, depth: usize, alpha: i32, beta: i32)
+             -> Option<i32> {
+        let mut b = b.clone();
+        match b.make_legal_move(m) {
+            GameState::Won => Some(WIN),
+            GameState::Drawn => Some(0),
+            GameState::Ongoing => {
+                let score = -self.negamax(&b, depth, -beta, -alpha)?;
+                Some(score - score.signum())
+            },
+        }
+    }
+
+    fn negamax(&mut self, b: &Board, depth: usize, mut alpha: i32, beta: i32) -> Option<i32> {
+        if self.budget.is_exhausted(self.start, self.nodes) { return None; }
+        self.nodes += 1;
+        if b.legal_moves_iter().any(|m| m.is_winning()) { return Some(WIN); }
+        if depth == 0 { return Some(0); }
+        let mut best = -i32::MAX;
+        for m in b.legal_moves_iter() {
+            let score = self.child(b, m, depth - 1, alpha, beta)?;
+            if score > best { best = score; }
+            if score > alpha { alpha = score; }
+            if alpha >= beta { break; }
+        }
+        Some(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alphabeta_drawn_board() {
+        let b = Board::generate(4, 16);
+        let mut player = AlphaBetaPlayer::new(Budget::Iterations(100));
+        assert!(matches!(player.choose(&b), Err(PlayerError::Engine(..))));
+    }
+}
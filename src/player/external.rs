@@ -0,0 +1,196 @@
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use board::{self, Board, LegalMove};
+use player::{Budget, Decision, Player, PlayerError};
+use protocol;
+
+#[derive(Debug)]
+pub enum ExternalError {
+    Io(io::Error),
+    Timeout,
+    Disconnected,
+    Rejected(String),
+    Protocol(String),
+    Board(board::Error),
+}
+
+impl fmt::Display for ExternalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExternalError::Io(ref e) => write!(f, "Error: {}", e),
+            ExternalError::Timeout => write!(f, "Error: engine timed out"),
+            ExternalError::Disconnected => write!(f, "Error: engine exited"),
+            ExternalError::Rejected(ref s) => write!(f, "Error: engine rejected command: {}", s),
+            ExternalError::Protocol(ref s) =>
+                write!(f, "Error: unexpected engine reply: {}", s),
+            ExternalError::Board(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ExternalError {
+    fn description(&self) -> &str {
+        match *self {
+            ExternalError::Io(..) => "I/O error",
+            ExternalError::Timeout => "Engine timed out",
+            ExternalError::Disconnected => "Engine exited",
+            ExternalError::Rejected(..) => "Engine rejected command",
+            ExternalError::Protocol(..) => "Unexpected engine reply",
+            ExternalError::Board(..) => "Board error",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            ExternalError::Io(ref e) => Some(e),
+            ExternalError::Board(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExternalError {
+    fn from(e: io::Error) -> ExternalError { ExternalError::Io(e) }
+}
+
+impl From<board::Error> for ExternalError {
+    fn from(e: board::Error) -> ExternalError { ExternalError::Board(e) }
+}
+
+impl From<ExternalError> for PlayerError {
+    fn from(e: ExternalError) -> PlayerError {
+        match e {
+            ExternalError::Io(e) => PlayerError::Io(e),
+            e => PlayerError::Engine(e.to_string().trim_start_matches("Error: ").to_string()),
+        }
+    }
+}
+
+struct Process {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    lines: Receiver<String>,
+    broken: bool,
+}
+
+impl Process {
+    fn command(&mut self, line: &str, timeout: Duration) -> Result<String, ExternalError> {
+        if self.broken { return Err(ExternalError::Disconnected); }
+        let result = self.exchange(line, timeout);
+        // After a timeout or garbled reply the stream can no longer be trusted.
+        match result {
+            Ok(..) | Err(ExternalError::Rejected(..)) => (),
+            Err(..) => self.broken = true,
+        }
+        result
+    }
+
+    fn exchange(&mut self, line: &str, timeout: Duration) -> Result<String, ExternalError> {
+        let stdin = self.stdin.as_mut().ok_or(ExternalError::Disconnected)?;
+        writeln!(stdin, "{}", line)?;
+        stdin.flush()?;
+        let reply = match self.lines.recv_timeout(timeout) {
+            Ok(reply) => reply,
+            Err(RecvTimeoutError::Timeout) => return Err(ExternalError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => return Err(ExternalError::Disconnected),
+        };
+        let reply = reply.trim_end();
+        if reply == "=" {
+            Ok(String::new())
+        } else if let Some(s) = reply.strip_prefix("= ") {
+            Ok(String::from(s))
+        } else if let Some(s) = reply.strip_prefix("? ") {
+            Err(ExternalError::Rejected(String::from(s)))
+        } else {
+            Err(ExternalError::Protocol(String::from(reply)))
+        }
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        if self.broken || self.command("quit", Duration::from_secs(1)).is_err() {
+            let _ = self.child.kill();
+        }
+        self.stdin = None;
+        let _ = self.child.wait();
+    }
+}
+
+// An engine running in a subprocess and speaking `protocol`. Each move sends
+// the whole position, so the engine needs no other state. If the engine
+// misbehaves, `choose` fails and the game is abandoned.
+pub struct ExternalPlayer {
+    name: String,
+    budget: Option<Budget>,
+    timeout: Duration,
+    process: Process,
+}
+
+impl ExternalPlayer {
+    // Without a `budget` the engine searches for as long as it likes.
+    pub fn spawn(mut command: Command, budget: Option<Budget>)
+                 -> Result<Self, ExternalError> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                match line {
+                    Ok(line) => if tx.send(line).is_err() { break; },
+                    Err(..) => break,
+                }
+            }
+        });
+        let process = Process { child, stdin: Some(stdin), lines, broken: false };
+        let timeout = match budget {
+            Some(Budget::Time(t)) => t + Duration::from_secs(5),
+            _ => Duration::from_secs(60),
+        };
+        let mut player = ExternalPlayer {
+            name: String::new(),
+            budget,
+            timeout,
+            process,
+        };
+        let version = player.command("protocol_version")?;
+        if version != protocol::VERSION.to_string() {
+            return Err(ExternalError::Protocol(version));
+        }
+        player.name = player.command("name")?;
+        Ok(player)
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn command(&mut self, line: &str) -> Result<String, ExternalError> {
+        self.process.command(line, self.timeout)
+    }
+
+    pub fn request(&mut self, b: &Board) -> Result<LegalMove, ExternalError> {
+        self.command(&format!("position {}", b.to_notation()))?;
+        let reply = match self.budget {
+            Some(Budget::Time(t)) => self.command(&format!("genmove {}", t.as_millis()))?,
+            Some(Budget::Iterations(n)) => self.command(&format!("genmove playouts {}", n))?,
+            None => self.command("genmove")?,
+        };
+        Ok(b.parse_move(&reply)?)
+    }
+}
+
+impl Player for ExternalPlayer {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        Ok(Decision::Move(self.request(b)?))
+    }
+
+    fn name(&self) -> String { self.name.clone() }
+}
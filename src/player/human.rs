@@ -1,50 +1,130 @@
-use std::io::{self, Write};
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::Duration;
 
-use board::{Board, LegalMove, Move, Side};
-use player::Player;
+use board::Board;
+use player::{Budget, Decision, MCTSPlayer, Player, PlayerError};
 
-pub struct HumanPlayer;
+const HELP: &str = "\
+Enter a move as a side and position, e.g. n4, or one of:
+  undo    take back your last move
+  hint    ask the engine for a suggestion
+  resign  give up the game
+  draw    offer a draw
+  show    print the board
+  quit    stop the game";
+
+// Reads commands a line at a time. End of input aborts the game rather than
+// waiting forever.
+pub struct HumanPlayer {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    hint: MCTSPlayer,
+}
 
 impl HumanPlayer {
-    fn get_side(_: &Board) -> Side {
-        loop {
-            print!("Side {{n,e,s,w}}? ");
-            io::stdout().flush().unwrap();
-            let mut buffer = String::new();
-            io::stdin().read_line(&mut buffer).unwrap();
-            match buffer.trim() {
-                "n" => return Side::North,
-                "e" => return Side::East,
-                "s" => return Side::South,
-                "w" => return Side::West,
-                _ => println!("Invalid side!"),
-            }
+    pub fn new() -> HumanPlayer {
+        HumanPlayer::with_io(Box::new(BufReader::new(io::stdin())), Box::new(io::stdout()))
+    }
+
+    pub fn with_io(input: Box<dyn BufRead>, output: Box<dyn Write>) -> HumanPlayer {
+        HumanPlayer {
+            input,
+            output,
+            hint: MCTSPlayer::new(Duration::from_secs(1)),
         }
     }
 
-    fn get_pos(b: &Board) -> usize {
-        loop {
-            print!("Position [0-{}]? ", b.size() - 1);
-            io::stdout().flush().unwrap();
-            let mut buffer = String::new();
-            io::stdin().read_line(&mut buffer).unwrap();
-            match buffer.trim().parse::<usize>() {
-                Ok(pos) => return pos,
-                Err(..) => println!("Invalid position!"),
-            }
+    // Hints search for one second unless given another budget.
+    pub fn set_hint_budget(&mut self, budget: Budget) {
+        self.hint = MCTSPlayer::with_budget(budget);
+    }
+
+    // Returns `None` at the end of input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        write!(self.output, "{}", prompt)?;
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            writeln!(self.output)?;
+            return Ok(None);
         }
+        Ok(Some(line.trim().to_lowercase()))
     }
 }
 
+impl Default for HumanPlayer {
+    fn default() -> HumanPlayer { HumanPlayer::new() }
+}
+
 impl Player for HumanPlayer {
-    fn choose(&self, b: &Board) -> LegalMove {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        let prompt = format!("{} to move (n4, undo, hint, resign, draw, show, quit, help)? ",
+                             b.active());
+        loop {
+            let line = self.read_line(&prompt)?.ok_or(PlayerError::Aborted)?;
+            match line.as_str() {
+                "" => (),
+                "undo" => return Ok(Decision::Undo),
+                "resign" => return Ok(Decision::Resign),
+                "draw" => return Ok(Decision::OfferDraw),
+                "quit" => return Err(PlayerError::Aborted),
+                "show" => write!(self.output, "{}", b)?,
+                "help" | "?" => writeln!(self.output, "{}", HELP)?,
+                "hint" => {
+                    let info = self.hint.search(b);
+                    writeln!(self.output, "Hint: {} ({})", info.m, info.eval)?;
+                },
+                s => match b.parse_move(s) {
+                    Ok(m) => return Ok(Decision::Move(m)),
+                    Err(e) => writeln!(self.output, "{}", e)?,
+                },
+            }
+        }
+    }
+
+    fn name(&self) -> String { String::from("Human") }
+
+    fn accept_draw(&mut self, _b: &Board) -> bool {
         loop {
-            let side = HumanPlayer::get_side(b);
-            let pos = HumanPlayer::get_pos(b);
-            match Move::new(side, pos).annotated(b) {
-                Some(m) => return m,
-                None => println!("Illegal move!"),
+            match self.read_line("Your opponent offers a draw. Accept (y/n)? ") {
+                Ok(Some(ref s)) if s == "y" || s == "yes" => return true,
+                Ok(Some(ref s)) if s == "n" || s == "no" => return false,
+                Ok(Some(..)) => (),
+                _ => return false,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    fn human(input: &str) -> HumanPlayer {
+        let mut p = HumanPlayer::with_io(Box::new(Cursor::new(String::from(input))),
+                                         Box::new(io::sink()));
+        p.set_hint_budget(Budget::Iterations(50));
+        p
+    }
+
+    #[test]
+    fn human_commands() {
+        let b = Board::new(4);
+        let mut p = human("bogus\nq9\n\nshow\nhint\nhelp\nN1\nundo\nresign\ndraw\nquit\n");
+        assert_eq!(Decision::Move(b.parse_move("n1").unwrap()), p.choose(&b).unwrap());
+        assert_eq!(Decision::Undo, p.choose(&b).unwrap());
+        assert_eq!(Decision::Resign, p.choose(&b).unwrap());
+        assert_eq!(Decision::OfferDraw, p.choose(&b).unwrap());
+        assert!(matches!(p.choose(&b), Err(PlayerError::Aborted)));
+        assert!(matches!(p.choose(&b), Err(PlayerError::Aborted)));
+    }
+
+    #[test]
+    fn human_accept_draw() {
+        let b = Board::new(4);
+        assert!(human("maybe\nY\n").accept_draw(&b));
+        assert!(!human("n\n").accept_draw(&b));
+        assert!(!human("").accept_draw(&b));
+    }
+}
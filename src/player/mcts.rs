@@ -1,38 +1,227 @@
+use std::fmt;
 use std::iter;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use rand::{self, Rng};
 use rand::distributions::IndependentSample;
 use rand::distributions::gamma::Gamma;
 
-use board::{Board, LegalMove, GameState};
-use player::Player;
+use board::{Board, LegalMove, GameState, MoveId};
+use book::Book;
+use eval::{Estimate, Evaluator};
+use player::{Budget, Decision, Player, PlayerError};
+use policy::Policy;
 
 // Jeffrey's prior
 const PRIOR: f64 = 0.5;
 
+#[derive(Clone)]
 pub struct MCTSPlayer {
-    dur: Duration,
+    budget: Budget,
+    evaluator: Option<Arc<dyn Evaluator>>,
+    weight: f64,
+    policy: Option<Policy>,
+    book: Option<Book>,
+    last: Option<SearchInfo>,
+    booked: bool,
 }
 
 impl MCTSPlayer {
     pub fn new(dur: Duration) -> Self {
-        MCTSPlayer { dur }
+        MCTSPlayer::with_budget(Budget::Time(dur))
     }
-}
 
-impl Player for MCTSPlayer {
-    fn choose(&self, b: &Board) -> LegalMove {
+    pub fn with_budget(budget: Budget) -> Self {
+        MCTSPlayer {
+            budget,
+            evaluator: None,
+            weight: 0.0,
+            policy: None,
+            book: None,
+            last: None,
+            booked: false,
+        }
+    }
+
+    // Scores new leaves as `weight` times the evaluator's value plus the
+    // remainder times a random rollout; a weight of 1.0 skips rollouts. The
+    // evaluator's priors also decide which unvisited moves are tried first.
+    pub fn set_evaluator(&mut self, evaluator: Box<dyn Evaluator>, weight: f64) {
+        self.evaluator = Some(Arc::from(evaluator));
+        self.weight = weight;
+    }
+
+    pub fn budget(&self) -> Budget { self.budget }
+
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
+    // Rollouts sample moves from `policy` instead of uniformly.
+    pub fn set_rollout_policy(&mut self, policy: Policy) {
+        self.policy = Some(policy);
+    }
+
+    // `think` plays from `book` without searching while the position is
+    // in it.
+    pub fn set_book(&mut self, book: Book) {
+        self.book = Some(book);
+    }
+
+    fn run(&self, b: &Board) -> (Node, SearchInfo) {
         let now = Instant::now();
         let mut rng = rand::thread_rng();
+        let leaf = Leaf {
+            evaluator: self.evaluator.as_deref(),
+            weight: self.weight,
+            policy: self.policy.as_ref(),
+        };
         let mut node = Node::Unvisited;
+        let mut n = 0;
         loop {
-            node.explore(&mut rng, b.clone());
-            if node.is_certain() || now.elapsed() >= self.dur {
+            node.explore(&mut rng, leaf, b.clone());
+            n += 1;
+            if node.is_certain() || self.budget.is_exhausted(now, n) {
                 break;
             }
         }
-        node.best_move(b)
+        let (m, eval) = node.best_move(b);
+        (node, SearchInfo { m, eval, nplay: n, elapsed: now.elapsed() })
+    }
+
+    // `b` must have a legal move; `think` checks this for its callers.
+    pub fn search(&self, b: &Board) -> SearchInfo {
+        self.run(b).1
+    }
+
+    // Like `search`, but also reports statistics for each of the root's
+    // children, in `legal_moves_iter` order. When the root was solved only the
+    // chosen move is reported.
+    pub fn analyse(&self, b: &Board) -> (SearchInfo, Vec<ChildStats>) {
+        let (node, info) = self.run(b);
+        let children = match node {
+            Node::Probabilistic(ref p) => p.children.iter().zip(p.ids.iter())
+                .map(|(node, &id)| ChildStats {
+                    m: b.legal_move(id).unwrap(),
+                    expected: node.expected_score(),
+                    visits: node.visits(),
+                }).collect(),
+            _ => {
+                let expected = match info.eval {
+                    Evaluation::Expected(p) => p,
+                    Evaluation::Loss(..) => 0.0,
+                    Evaluation::Win(..) => 1.0,
+                    Evaluation::Draw(..) => 0.5,
+                };
+                vec![ChildStats { m: info.m, expected, visits: info.nplay }]
+            },
+        };
+        (info, children)
+    }
+
+    // Picks a move from the book, or failing that by searching. `last`
+    // reports the search, if there was one. Returns `None` if `b` has no
+    // legal moves.
+    pub fn think(&mut self, b: &Board) -> Option<LegalMove> {
+        self.last = None;
+        self.booked = false;
+        b.legal_moves_iter().next()?;
+        let booked = self.book.as_ref().and_then(|book| book.choose(b, &mut rand::thread_rng()));
+        self.booked = booked.is_some();
+        if booked.is_some() { return booked; }
+        let info = self.search(b);
+        self.last = Some(info);
+        Some(info.m)
+    }
+
+    pub fn last(&self) -> Option<SearchInfo> { self.last }
+}
+
+impl Player for MCTSPlayer {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        self.think(b).map(Decision::Move)
+            .ok_or_else(|| PlayerError::Engine(String::from("no legal moves")))
+    }
+
+    fn name(&self) -> String { String::from("MCTS") }
+
+    fn status(&self) -> Option<String> {
+        if self.booked { return Some(String::from("Playing a book move")); }
+        self.last.map(|info| match info.eval {
+            Evaluation::Expected(p) =>
+                format!("Choosing move with E[S] = {:.2} after {} playthroughs", p, info.nplay),
+            Evaluation::Loss(depth) => format!("Certain loss in {} move(s)", depth),
+            Evaluation::Win(depth) => format!("Certain win in {} move(s)", depth),
+            Evaluation::Draw(depth) => format!("Certain draw in {} move(s)", depth),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Evaluation {
+    Expected(f64),
+    Loss(usize),
+    Win(usize),
+    Draw(usize),
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Evaluation::Expected(p) => write!(f, "{:.3}", p),
+            Evaluation::Loss(depth) => write!(f, "loss {}", depth),
+            Evaluation::Win(depth) => write!(f, "win {}", depth),
+            Evaluation::Draw(depth) => write!(f, "draw {}", depth),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchInfo {
+    pub m: LegalMove,
+    pub eval: Evaluation,
+    pub nplay: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = self.elapsed.as_secs() * 1000 + u64::from(self.elapsed.subsec_millis());
+        write!(f, "move {} eval {} playouts {} time {}", self.m, self.eval, self.nplay, ms)
+    }
+}
+
+// `expected` is exactly 1.0 or 0.0 once the search has proved that the
+// move wins or loses; otherwise it is strictly between them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChildStats {
+    pub m: LegalMove,
+    pub expected: f64,
+    pub visits: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Leaf<'a> {
+    evaluator: Option<&'a dyn Evaluator>,
+    weight: f64,
+    policy: Option<&'a Policy>,
+}
+
+impl<'a> Leaf<'a> {
+    fn evaluate(&self, b: &Board) -> Option<Estimate> {
+        self.evaluator.and_then(|e| e.evaluate(b))
+    }
+
+    // Scores a new node for the player who moved into it, from `estimate` of
+    // its position and a rollout from `b`, the position after one more move.
+    fn score<R: Rng>(&self, rng: &mut R, estimate: Option<&Estimate>, b: Board) -> f64 {
+        match estimate {
+            Some(estimate) if self.weight >= 1.0 => 1.0 - estimate.value,
+            Some(estimate) => self.weight * (1.0 - estimate.value)
+                + (1.0 - self.weight) * Node::choose_unvisited_rest(rng, self.policy, b),
+            None => Node::choose_unvisited_rest(rng, self.policy, b),
+        }
     }
 }
 
@@ -53,7 +242,7 @@ enum Node {
 }
 
 impl Node {
-    pub fn is_certain(&self) -> bool {
+    fn is_certain(&self) -> bool {
         match *self {
             Node::Unvisited => false,
             Node::Probabilistic(..) => false,
@@ -63,30 +252,25 @@ impl Node {
         }
     }
 
-    pub fn best_move(&self, b: &Board) -> LegalMove {
+    fn best_move(&self, b: &Board) -> (LegalMove, Evaluation) {
         match *self {
             Node::Unvisited => panic!("node is unvisited"),
             Node::Probabilistic(ref p) => p.best_move(b),
-            Node::CertainLoss(ref c) => {
-                println!("Certain loss in {} move(s)", c.depth);
-                c.best_move(b)
-            },
-            Node::CertainWin(ref c) => {
-                println!("Certain win in {} move(s)", c.depth);
-                c.best_move(b)
-            },
-            Node::CertainDraw(ref c) => {
-                println!("Certain draw in {} move(s)", c.depth);
-                c.best_move(b)
-            },
+            Node::CertainLoss(ref c) => (c.best_move(b), Evaluation::Loss(c.depth)),
+            Node::CertainWin(ref c) => (c.best_move(b), Evaluation::Win(c.depth)),
+            Node::CertainDraw(ref c) => (c.best_move(b), Evaluation::Draw(c.depth)),
         }
     }
 
-    pub fn explore<R: Rng>(&mut self, rng: &mut R, b: Board) -> f64 {
+    fn explore<R: Rng>(&mut self, rng: &mut R, leaf: Leaf, b: Board) -> f64 {
         let result = match *self {
-            Node::Unvisited => self.explore_unvisted(rng, b),
-            Node::Probabilistic(ref mut p) => p.explore(rng, b),
-            _ => Finding::Score(self.score()),
+            Node::Unvisited => self.explore_unvisted(rng, leaf, b),
+            Node::Probabilistic(ref mut p) => p.explore(rng, leaf, b),
+            Node::CertainLoss(ref mut c) | Node::CertainWin(ref mut c)
+                | Node::CertainDraw(ref mut c) => {
+                c.visits += 1;
+                Finding::Score(self.score())
+            },
         };
         match result {
             Finding::Score(score) => score,
@@ -105,38 +289,47 @@ impl Node {
         }
     }
 
-    fn explore_unvisted<R: Rng>(&mut self, rng: &mut R, mut b: Board) -> Finding {
-        let (n, i, m) = Node::choose_unvisited_first(rng, &b);
+    fn explore_unvisted<R: Rng>(&mut self, rng: &mut R, leaf: Leaf, mut b: Board) -> Finding {
+        let (ids, m) = Node::choose_unvisited_first(rng, &b);
+        let estimate = leaf.evaluate(&b);
         match b.make_legal_move(m) {
-            GameState::Won => Finding::Replace(Node::CertainWin(Certain::new(1, i))),
-            GameState::Drawn => Finding::Replace(Node::CertainDraw(Certain::new(1, i))),
+            GameState::Won => Finding::Replace(Node::CertainWin(Certain::new(1, m.id(), 1))),
+            GameState::Drawn => Finding::Replace(Node::CertainDraw(Certain::new(1, m.id(), 1))),
             GameState::Ongoing => {
-                let score = Node::choose_unvisited_rest(rng, b);
-                let node = Node::Probabilistic(Probabilistic::new(n, score));
+                let score = leaf.score(rng, estimate.as_ref(), b);
+                let priors = estimate.map(|e| e.priors.into_boxed_slice());
+                let node = Node::Probabilistic(Probabilistic::new(ids, priors, score));
                 Finding::Both(node, score)
             }
         }
     }
 
-    fn choose_unvisited_first<R: Rng>(rng: &mut R, b: &Board) -> (usize, usize, LegalMove) {
+    // Returns the ids of all legal moves and a random one of them to play,
+    // preferring a winning move.
+    fn choose_unvisited_first<R: Rng>(rng: &mut R, b: &Board) -> (Vec<MoveId>, LegalMove) {
+        let mut ids = Vec::new();
         let mut m = None;
-        let mut i = 0;
-        let mut n = 0;
-        for (i1, m1) in b.legal_moves_iter().enumerate() {
-            n += 1;
-            if m1.is_winning() { return (n, i1, m1); }
-            if rng.gen_range(0, n) == 0 {
-                i = i1;
-                m = Some(m1);
-            }
+        let mut winning = None;
+        for m1 in b.legal_moves_iter() {
+            ids.push(m1.id());
+            if winning.is_some() { continue; }
+            if m1.is_winning() { winning = Some(m1); continue; }
+            if rng.gen_range(0, ids.len()) == 0 { m = Some(m1); }
         }
-        (n, i, m.unwrap())
+        (ids, winning.or(m).expect("no legal moves"))
     }
 
-    fn choose_unvisited_rest<R: Rng>(rng: &mut R, mut b: Board) -> f64 {
+    fn choose_unvisited_rest<R: Rng>(rng: &mut R, policy: Option<&Policy>, mut b: Board) -> f64 {
         let mut score = 1.0;
         loop {
-            let m = super::choose_winning_or_random(&b, rng);
+            let m = match policy {
+                Some(policy) => policy.choose(&b, rng),
+                None => super::choose_winning_or_random(&b, rng),
+            };
+            let m = match m {
+                Some(m) => m,
+                None => return 0.5,
+            };
             match b.make_legal_move(m) {
                 GameState::Won => return score,
                 GameState::Drawn => return 0.5,
@@ -155,9 +348,20 @@ impl Node {
         }
     }
 
-    fn expected_score_sample<R: Rng>(&self, rng: &mut R) -> f64 {
+    fn visits(&self) -> usize {
         match *self {
-            Node::Unvisited => beta_sample(rng, PRIOR, PRIOR),
+            Node::Unvisited => 0,
+            Node::Probabilistic(ref p) => p.visits(),
+            Node::CertainLoss(ref c) | Node::CertainWin(ref c) | Node::CertainDraw(ref c) =>
+                c.visits,
+        }
+    }
+
+    // `prior` is the chance that an unvisited node is the best move; 0.5
+    // gives Jeffrey's prior.
+    fn expected_score_sample<R: Rng>(&self, rng: &mut R, prior: f64) -> f64 {
+        match *self {
+            Node::Unvisited => beta_sample(rng, 2.0 * PRIOR * prior, 2.0 * PRIOR * (1.0 - prior)),
             Node::Probabilistic(ref p) => p.expected_score_sample(rng),
             _ => self.expected_score(),
         }
@@ -173,30 +377,32 @@ impl Node {
         }
     }
 
-    fn rank_key<R: Rng>(&self, rng: &mut R) -> (f64, isize) {
-        let p = self.expected_score_sample(rng);
+    fn rank_key<R: Rng>(&self, rng: &mut R, prior: f64) -> (f64, isize) {
+        let p = self.expected_score_sample(rng, prior);
         let d = self.rank_discriminator();
         (p, d)
     }
 }
 
+// `visits` carries on from the playouts the node had before it was solved.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 struct Certain {
     depth: usize,
-    index: usize,
+    id: MoveId,
+    visits: usize,
 }
 
 impl Certain {
-    fn new(depth: usize, index: usize) -> Self {
-        Certain { depth, index }
+    fn new(depth: usize, id: MoveId, visits: usize) -> Self {
+        Certain { depth, id, visits }
     }
 
-    fn parent(&self, index: usize) -> Self {
-        Certain::new(self.depth + 1, index)
+    fn parent(&self, id: MoveId, visits: usize) -> Self {
+        Certain::new(self.depth + 1, id, visits)
     }
 
     fn best_move(&self, b: &Board) -> LegalMove {
-        b.legal_moves_iter().nth(self.index).unwrap()
+        b.legal_move(self.id).unwrap()
     }
 }
 
@@ -205,53 +411,61 @@ impl Certain {
 struct Probabilistic {
     score: f64,
     nplay: f64,
+    ids: Box<[MoveId]>,
+    priors: Option<Box<[f64]>>,
     children: Box<[Node]>,
 }
 
 impl Probabilistic {
-    fn new(nchildren: usize, score: f64) -> Self {
+    fn new(ids: Vec<MoveId>, priors: Option<Box<[f64]>>, score: f64) -> Self {
         let score = PRIOR + score;
         let nplay = PRIOR + PRIOR + 1.0;
-        let children = iter::repeat(Node::Unvisited).
-            take(nchildren).collect::<Vec<Node>>().into_boxed_slice();
-        Probabilistic { score, nplay, children }
+        let children = iter::repeat_n(Node::Unvisited, ids.len()).
+            collect::<Vec<Node>>().into_boxed_slice();
+        let priors = priors.filter(|p| p.len() == ids.len());
+        Probabilistic { score, nplay, ids: ids.into_boxed_slice(), priors, children }
     }
 
-    fn best_move(&self, b: &Board) -> LegalMove {
-        let (p, m) = self.children.iter().map(Node::expected_score).zip(b.legal_moves_iter()).
+    fn best_move(&self, b: &Board) -> (LegalMove, Evaluation) {
+        let (p, &id) = self.children.iter().map(Node::expected_score).zip(self.ids.iter()).
             max_by(|&(p1, _), &(p2, _)| p1.partial_cmp(&p2).unwrap()).unwrap();
-        let n = self.nplay as usize;
-        println!("Choosing move with E[S] = {:.2} after {} playthroughs", p, n);
-        m
+        (b.legal_move(id).unwrap(), Evaluation::Expected(p))
     }
 
     fn expected_score(&self) -> f64 {
         self.score / self.nplay
     }
 
+    fn visits(&self) -> usize {
+        (self.nplay - PRIOR - PRIOR) as usize
+    }
+
     fn expected_score_sample<R: Rng>(&self, rng: &mut R) -> f64 {
         beta_sample(rng, self.score, self.nplay - self.score)
     }
 
-    fn explore<R: Rng>(&mut self, rng: &mut R, mut b: Board) -> Finding {
+    fn explore<R: Rng>(&mut self, rng: &mut R, leaf: Leaf, mut b: Board) -> Finding {
         let nall = self.children.len();
+        let visits = self.visits() + 1;
+        let priors = self.priors.as_deref();
         let mut nbad = 0;
-        let (_, i, node) = self.children.iter_mut().enumerate().map(|(i, node)| {
+        let (_, id, node) = self.children.iter_mut().zip(self.ids.iter()).enumerate()
+            .map(|(i, (node, &id))| {
             match *node { Node::CertainWin(..) | Node::CertainDraw(..) => nbad += 1, _ => () };
-            (node.rank_key(rng), i, node)
+            (node.rank_key(rng, scaled_prior(priors, i)), id, node)
         }).max_by(|&(k1, _, _), &(k2, _, _)| {
             k1.partial_cmp(&k2).unwrap()
         }).unwrap();
         match *node {
-            Node::CertainLoss(ref c) => Finding::Replace(Node::CertainWin(c.parent(i))),
+            Node::CertainLoss(ref c) => Finding::Replace(Node::CertainWin(c.parent(id, visits))),
             Node::CertainWin(ref c) if nbad == nall =>
-                Finding::Replace(Node::CertainLoss(c.parent(i))),
+                Finding::Replace(Node::CertainLoss(c.parent(id, visits))),
             Node::CertainDraw(ref c) if nbad == nall =>
-                Finding::Replace(Node::CertainDraw(c.parent(i))),
+                Finding::Replace(Node::CertainDraw(c.parent(id, visits))),
             _ => {
-                let m = b.legal_moves_iter().nth(i).unwrap();
+                let m = b.legal_move(id).unwrap();
                 b.make_legal_move(m);
-                let score = 1.0 - node.explore(rng, b);
+                let score = 1.0 - node.explore(rng, leaf, b);
                 self.score += score;
                 self.nplay += 1.0;
                 Finding::Score(score)
@@ -260,6 +474,15 @@ impl Probabilistic {
     }
 }
 
+// Scales a move's prior so that uniform priors give 0.5, whatever the number
+// of moves.
+fn scaled_prior(priors: Option<&[f64]>, i: usize) -> f64 {
+    priors.map_or(0.5, |p| {
+        let x = p.len() as f64 * p[i];
+        (x / (1.0 + x)).clamp(0.001, 0.999)
+    })
+}
+
 fn beta_sample<R: Rng>(rng: &mut R, alpha: f64, beta: f64) -> f64 {
     if alpha <= 1.0 && beta <= 1.0 {
         loop {
@@ -284,3 +507,36 @@ fn beta_sample<R: Rng>(rng: &mut R, alpha: f64, beta: f64) -> f64 {
         a / (a + b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Evaluator for Counting {
+        fn evaluate(&self, b: &Board) -> Option<Estimate> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let n = b.legal_moves_iter().count();
+            Some(Estimate { value: 0.5, priors: vec![1.0 / n as f64; n] })
+        }
+    }
+
+    #[test]
+    fn mcts_evaluates_once_per_expansion() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut player = MCTSPlayer::with_budget(Budget::Iterations(50));
+        player.set_evaluator(Box::new(Counting(calls.clone())), 0.5);
+        let info = player.search(&Board::new(6));
+        assert!(calls.load(Ordering::SeqCst) <= info.nplay);
+    }
+
+    #[test]
+    fn mcts_drawn_board() {
+        let b = Board::generate(4, 16);
+        let mut player = MCTSPlayer::with_budget(Budget::Iterations(100));
+        assert!(matches!(player.choose(&b), Err(PlayerError::Engine(..))));
+        assert_eq!(None, player.last());
+    }
+}
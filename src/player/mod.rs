@@ -1,26 +1,121 @@
+mod alphabeta;
+mod external;
 mod human;
 mod mcts;
+mod network;
 mod random;
 
+use std::error;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
 use rand::Rng;
 
-use board::{Board, LegalMove};
+use board::{Board, LegalMove, Outcome};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Move(LegalMove),
+    // Take back the player's last move and the reply to it.
+    Undo,
+    Resign,
+    OfferDraw,
+}
+
+#[derive(Debug)]
+pub enum PlayerError {
+    // The player stopped the game, e.g. a human quitting.
+    Aborted,
+    Io(io::Error),
+    Engine(String),
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlayerError::Aborted => write!(f, "Error: game aborted"),
+            PlayerError::Io(ref e) => write!(f, "Error: {}", e),
+            PlayerError::Engine(ref s) => write!(f, "Error: {}", s),
+        }
+    }
+}
+
+impl error::Error for PlayerError {
+    fn description(&self) -> &str {
+        match *self {
+            PlayerError::Aborted => "Game aborted",
+            PlayerError::Io(..) => "I/O error",
+            PlayerError::Engine(..) => "Engine error",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            PlayerError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlayerError {
+    fn from(e: io::Error) -> PlayerError { PlayerError::Io(e) }
+}
 
+// Besides choosing moves, a player hears about the game's progress through
+// the hooks, which do nothing by default.
 pub trait Player {
-    fn choose(&self, b: &Board) -> LegalMove;
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError>;
+
+    fn name(&self) -> String { String::from("?") }
+
+    // A line about the player's last decision, such as search statistics.
+    fn status(&self) -> Option<String> { None }
+
+    // Called before the first move, and again whenever moves are taken back.
+    fn new_game(&mut self, _b: &Board) {}
+
+    // `b` is the position after the opponent's move `m`.
+    fn opponent_moved(&mut self, _b: &Board, _m: LegalMove) {}
+
+    fn accept_draw(&mut self, _b: &Board) -> bool { false }
+
+    // `result` is `None` if the game was aborted.
+    fn game_over(&mut self, _b: &Board, _result: Option<Outcome>) {}
+}
+
+// Per-move search limit: wall-clock time, or a count of MCTS playouts or
+// alpha-beta nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Budget {
+    Time(Duration),
+    Iterations(usize),
+}
+
+impl Budget {
+    pub fn is_exhausted(&self, start: Instant, n: usize) -> bool {
+        match *self {
+            Budget::Time(dur) => start.elapsed() >= dur,
+            Budget::Iterations(max) => n >= max,
+        }
+    }
 }
 
-fn choose_winning_or_random<R: Rng>(b: &Board, rng: &mut R) -> LegalMove {
+// Returns `None` if there are no legal moves.
+pub fn choose_winning_or_random<R: Rng>(b: &Board, rng: &mut R) -> Option<LegalMove> {
     let mut iter = b.legal_moves_iter();
-    let mut m = iter.next().unwrap();
-    if m.is_winning() { return m; }
+    let mut m = iter.next()?;
+    if m.is_winning() { return Some(m); }
     for (i, m1) in iter.enumerate() {
-        if m1.is_winning() { return m1; }
+        if m1.is_winning() { return Some(m1); }
         if rng.gen_range(0, i + 2) == 0 { m = m1; }
     }
-    m
+    Some(m)
 }
 
+pub use self::alphabeta::AlphaBetaPlayer;
+pub use self::external::{ExternalError, ExternalPlayer};
 pub use self::random::RandomPlayer;
 pub use self::human::HumanPlayer;
-pub use self::mcts::MCTSPlayer;
+pub use self::mcts::{ChildStats, Evaluation, MCTSPlayer, SearchInfo};
+pub use self::network::{NetworkError, NetworkPlayer};
@@ -0,0 +1,402 @@
+// A remote opponent reached over TCP. Each side runs its own `Game` with a
+// `NetworkPlayer` in the peer's seat; moves and other decisions made locally
+// reach the peer through the player hooks.
+//
+// Messages are UTF-8 text frames, each preceded by its length as a 32-bit
+// big-endian integer. The host opens with
+//
+//   HELLO <version> <name>        answered by the guest's own HELLO
+//   GAME <seat> <position>        the guest's seat (x or o) and the start
+//                                 position in board notation
+//
+// and the guest confirms the layout with `READY <position>` as it parsed
+// it, or refuses with `REJECT <reason>`. After that either side may send
+//
+//   MOVE <move>    UNDO    RESIGN    DRAW    ACCEPT    DECLINE    QUIT
+//
+// where `DRAW` offers a draw and must be answered with `ACCEPT` or
+// `DECLINE`.
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use board::{self, Board, Entry, GameState, LegalMove, Outcome};
+use player::{Decision, Player, PlayerError};
+
+pub const VERSION: usize = 1;
+
+const MAX_FRAME: usize = 4096;
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    Disconnected,
+    Version(usize),
+    Rejected(String),
+    Protocol(String),
+    Board(board::Error),
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NetworkError::Io(ref e) => write!(f, "Error: {}", e),
+            NetworkError::Disconnected => write!(f, "Error: peer disconnected"),
+            NetworkError::Version(v) => write!(f, "Error: peer speaks protocol version {}", v),
+            NetworkError::Rejected(ref s) => write!(f, "Error: peer rejected the game: {}", s),
+            NetworkError::Protocol(ref s) => write!(f, "Error: unexpected message: {}", s),
+            NetworkError::Board(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for NetworkError {
+    fn description(&self) -> &str {
+        match *self {
+            NetworkError::Io(..) => "I/O error",
+            NetworkError::Disconnected => "Peer disconnected",
+            NetworkError::Version(..) => "Protocol version mismatch",
+            NetworkError::Rejected(..) => "Peer rejected the game",
+            NetworkError::Protocol(..) => "Unexpected message",
+            NetworkError::Board(..) => "Board error",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            NetworkError::Io(ref e) => Some(e),
+            NetworkError::Board(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> NetworkError { NetworkError::Io(e) }
+}
+
+impl From<board::Error> for NetworkError {
+    fn from(e: board::Error) -> NetworkError { NetworkError::Board(e) }
+}
+
+impl From<NetworkError> for PlayerError {
+    fn from(e: NetworkError) -> PlayerError {
+        match e {
+            NetworkError::Io(e) => PlayerError::Io(e),
+            e => PlayerError::Engine(e.to_string().trim_start_matches("Error: ").to_string()),
+        }
+    }
+}
+
+pub fn write_frame<W: Write>(w: &mut W, message: &str) -> io::Result<()> {
+    w.write_all(&(message.len() as u32).to_be_bytes())?;
+    w.write_all(message.as_bytes())?;
+    w.flush()
+}
+
+// Returns `None` if the stream ends cleanly before a frame.
+pub fn read_frame<R: Read>(r: &mut R) -> Result<Option<String>, NetworkError> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(NetworkError::Io(e)),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME { return Err(NetworkError::Protocol(String::from("frame too long"))); }
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+    String::from_utf8(buf).map(Some)
+        .map_err(|_| NetworkError::Protocol(String::from("frame is not UTF-8")))
+}
+
+fn seat_name(entry: Entry) -> &'static str {
+    if entry == Entry::Player1 { "x" } else { "o" }
+}
+
+pub struct NetworkPlayer {
+    stream: TcpStream,
+    // The seat the peer plays.
+    entry: Entry,
+    name: String,
+    started: bool,
+    // Set while the game handles an undo or draw offer that came from the
+    // peer, so the hooks do not send it back.
+    undoing: bool,
+    offered: bool,
+    // Set once the peer has quit or resigned, or the connection failed.
+    closed: bool,
+}
+
+impl NetworkPlayer {
+    fn new(stream: TcpStream, entry: Entry, name: String) -> NetworkPlayer {
+        NetworkPlayer {
+            stream,
+            entry,
+            name,
+            started: false,
+            undoing: false,
+            offered: false,
+            closed: false,
+        }
+    }
+
+    fn send(&mut self, message: &str) -> Result<(), NetworkError> {
+        if self.closed { return Err(NetworkError::Disconnected); }
+        write_frame(&mut self.stream, message).map_err(|e| {
+            self.closed = true;
+            NetworkError::Io(e)
+        })
+    }
+
+    fn receive(&mut self) -> Result<String, NetworkError> {
+        if self.closed { return Err(NetworkError::Disconnected); }
+        match read_frame(&mut self.stream) {
+            Ok(Some(message)) => Ok(message),
+            Ok(None) => {
+                self.closed = true;
+                Err(NetworkError::Disconnected)
+            },
+            Err(e) => {
+                self.closed = true;
+                Err(e)
+            },
+        }
+    }
+
+    fn hello(&mut self, local: &str) -> Result<(), NetworkError> {
+        self.send(&format!("HELLO {} {}", VERSION, local))?;
+        let message = self.receive()?;
+        let mut words = message.splitn(3, ' ');
+        match (words.next(), words.next().and_then(|v| v.parse::<usize>().ok())) {
+            (Some("HELLO"), Some(VERSION)) => (),
+            (Some("HELLO"), Some(v)) => return Err(NetworkError::Version(v)),
+            _ => return Err(NetworkError::Protocol(message.clone())),
+        }
+        self.name = words.next().unwrap_or("?").to_string();
+        Ok(())
+    }
+
+    // Offers the game starting from `b` to a guest who will play `remote`.
+    pub fn host(stream: TcpStream, b: &Board, remote: Entry, name: &str)
+                -> Result<NetworkPlayer, NetworkError> {
+        let mut p = NetworkPlayer::new(stream, remote, String::new());
+        p.hello(name)?;
+        let position = b.to_notation();
+        p.send(&format!("GAME {} {}", seat_name(remote), position))?;
+        let reply = p.receive()?;
+        match reply.split_at(reply.find(' ').unwrap_or(reply.len())) {
+            ("READY", echoed) if echoed.trim() == position => Ok(p),
+            ("READY", _) => Err(NetworkError::Protocol(reply.clone())),
+            ("REJECT", reason) => Err(NetworkError::Rejected(reason.trim().to_string())),
+            _ => Err(NetworkError::Protocol(reply.clone())),
+        }
+    }
+
+    // Joins a hosted game, returning the agreed start position. The local
+    // player takes the seat opposite `entry()`.
+    pub fn join(stream: TcpStream, name: &str) -> Result<(NetworkPlayer, Board), NetworkError> {
+        let mut p = NetworkPlayer::new(stream, Entry::Player1, String::new());
+        p.hello(name)?;
+        let message = p.receive()?;
+        let mut words = message.splitn(3, ' ');
+        let (seat, position) = match (words.next(), words.next(), words.next()) {
+            (Some("GAME"), Some(seat @ "x"), Some(position))
+            | (Some("GAME"), Some(seat @ "o"), Some(position)) => (seat, position),
+            _ => return Err(NetworkError::Protocol(message.clone())),
+        };
+        p.entry = if seat == "x" { Entry::Player2 } else { Entry::Player1 };
+        match position.parse::<Board>() {
+            Ok(b) => {
+                p.send(&format!("READY {}", b.to_notation()))?;
+                Ok((p, b))
+            },
+            Err(e) => {
+                let reason = e.to_string();
+                p.send(&format!("REJECT {}", reason.trim_start_matches("Error: ")))?;
+                Err(NetworkError::Board(e))
+            },
+        }
+    }
+
+    // Waits on `addr` for a guest and hosts the game for them, as `host`.
+    pub fn listen(addr: &str, b: &Board, remote: Entry, name: &str)
+                  -> Result<NetworkPlayer, NetworkError> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        NetworkPlayer::host(stream, b, remote, name)
+    }
+
+    // Joins the game hosted at `addr`, as `join`.
+    pub fn connect(addr: &str, name: &str) -> Result<(NetworkPlayer, Board), NetworkError> {
+        NetworkPlayer::join(TcpStream::connect(addr)?, name)
+    }
+
+    pub fn entry(&self) -> Entry { self.entry }
+
+    // The players of a game in seat order, with `local` opposite the peer.
+    pub fn seat(self, local: Box<dyn Player>) -> [Box<dyn Player>; 2] {
+        match self.entry {
+            Entry::Player1 => [Box::new(self), local],
+            _ => [local, Box::new(self)],
+        }
+    }
+
+    fn decide(&mut self, b: &Board) -> Result<Decision, NetworkError> {
+        if self.offered {
+            self.offered = false;
+            self.send("DECLINE")?;
+        }
+        let message = self.receive()?;
+        let mut words = message.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("MOVE"), Some(m)) => Ok(Decision::Move(b.parse_move(m)?)),
+            (Some("UNDO"), None) => {
+                self.undoing = true;
+                Ok(Decision::Undo)
+            },
+            (Some("RESIGN"), None) => {
+                self.closed = true;
+                Ok(Decision::Resign)
+            },
+            (Some("DRAW"), None) => {
+                self.offered = true;
+                Ok(Decision::OfferDraw)
+            },
+            (Some("QUIT"), None) => {
+                self.closed = true;
+                Err(NetworkError::Disconnected)
+            },
+            _ => Err(NetworkError::Protocol(message.clone())),
+        }
+    }
+}
+
+impl Player for NetworkPlayer {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        self.decide(b).map_err(|e| {
+            let _ = self.send("QUIT");
+            self.closed = true;
+            PlayerError::from(e)
+        })
+    }
+
+    fn name(&self) -> String { self.name.clone() }
+
+    fn new_game(&mut self, _b: &Board) {
+        if !self.started {
+            self.started = true;
+        } else if self.undoing {
+            self.undoing = false;
+        } else {
+            let _ = self.send("UNDO");
+        }
+    }
+
+    fn opponent_moved(&mut self, _b: &Board, m: LegalMove) {
+        let _ = self.send(&format!("MOVE {}", m));
+    }
+
+    fn accept_draw(&mut self, _b: &Board) -> bool {
+        if self.send("DRAW").is_err() { return false; }
+        match self.receive() {
+            Ok(ref reply) if reply == "ACCEPT" => true,
+            Ok(ref reply) if reply == "DECLINE" => false,
+            _ => {
+                self.closed = true;
+                false
+            },
+        }
+    }
+
+    fn game_over(&mut self, b: &Board, result: Option<Outcome>) {
+        if self.closed { return; }
+        let message = match result {
+            None => "QUIT",
+            Some(..) if b.state() != GameState::Ongoing => return,
+            Some(Outcome::Draw) if self.offered => "ACCEPT",
+            Some(Outcome::Draw) => return,
+            Some(Outcome::Win(winner)) if winner == self.entry => "RESIGN",
+            Some(Outcome::Win(..)) => return,
+        };
+        self.offered = false;
+        let _ = self.send(message);
+        self.closed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    use game::Game;
+    use player::RandomPlayer;
+
+    #[test]
+    fn network_frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, "MOVE N4").unwrap();
+        write_frame(&mut buf, "").unwrap();
+        assert_eq!(&[0, 0, 0, 7], &buf[..4]);
+        let mut r = &buf[..];
+        assert_eq!(Some(String::from("MOVE N4")), read_frame(&mut r).unwrap());
+        assert_eq!(Some(String::new()), read_frame(&mut r).unwrap());
+        assert_eq!(None, read_frame(&mut r).unwrap());
+        let mut r = &[0xff, 0xff, 0xff, 0xff][..];
+        assert!(read_frame(&mut r).is_err());
+    }
+
+    #[test]
+    fn network_loopback_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let b = Board::generate(6, 3);
+        let position = b.to_notation();
+        let guest = thread::spawn(move || {
+            let (remote, b) = NetworkPlayer::connect(&addr.to_string(), "guest").unwrap();
+            assert_eq!(Entry::Player1, remote.entry());
+            assert_eq!("host", remote.name());
+            let mut game = Game::new(b, remote.seat(Box::new(RandomPlayer)));
+            assert_eq!(Some("host"), game.record().player(Entry::Player1));
+            let outcome = game.run(|_, _| ());
+            (outcome, game.board().to_notation(), game.history().len())
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let remote = NetworkPlayer::host(stream, &b, Entry::Player2, "host").unwrap();
+        assert_eq!("guest", remote.name());
+        let players: [Box<dyn Player>; 2] = [Box::new(RandomPlayer), Box::new(remote)];
+        let mut game = Game::new(b, players);
+        let outcome = game.run(|_, _| ());
+        assert!(outcome.is_some());
+        assert_eq!(position, game.record().initial().to_notation());
+        let result = guest.join().unwrap();
+        assert_eq!((outcome, game.board().to_notation(), game.history().len()), result);
+    }
+
+    #[test]
+    fn network_undo_draw_resign() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let peer = thread::spawn(move || {
+            let stream = TcpStream::connect(addr).unwrap();
+            let (mut remote, b) = NetworkPlayer::join(stream, "guest").unwrap();
+            assert_eq!(Decision::Undo, remote.choose(&b).unwrap());
+            remote.new_game(&b);
+            assert_eq!(Decision::OfferDraw, remote.choose(&b).unwrap());
+            assert_eq!(Decision::Resign, remote.choose(&b).unwrap());
+            assert!(remote.choose(&b).is_err());
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let b = Board::new(4);
+        let mut remote = NetworkPlayer::host(stream, &b, Entry::Player2, "host").unwrap();
+        remote.new_game(&b);
+        remote.new_game(&b);
+        write_frame(&mut remote.stream, "DRAW").unwrap();
+        assert_eq!(Some(String::from("DECLINE")), read_frame(&mut remote.stream).unwrap());
+        write_frame(&mut remote.stream, "RESIGN").unwrap();
+        peer.join().unwrap();
+        assert_eq!(None, read_frame(&mut remote.stream).unwrap());
+    }
+}
@@ -1,12 +1,15 @@
 use rand;
 
-use board::{Board, LegalMove};
-use player::Player;
+use board::Board;
+use player::{Decision, Player, PlayerError};
 
 pub struct RandomPlayer;
 
 impl Player for RandomPlayer {
-    fn choose(&self, b: &Board) -> LegalMove {
-        super::choose_winning_or_random(b, &mut rand::thread_rng())
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        super::choose_winning_or_random(b, &mut rand::thread_rng()).map(Decision::Move)
+            .ok_or_else(|| PlayerError::Engine(String::from("no legal moves")))
     }
+
+    fn name(&self) -> String { String::from("Random") }
 }
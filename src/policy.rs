@@ -0,0 +1,258 @@
+// A linear move policy: each legal move gets a logit from a few handcrafted
+// features and moves are sampled from the softmax over those logits. Weights
+// are fitted to self-play samples by gradient descent on cross-entropy
+// against the search's visit distribution, and stored as text with one
+// `name weight` line per feature.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use board::{Board, LegalMove};
+use selfplay::Sample;
+
+pub const NFEATURES: usize = 10;
+
+pub const FEATURES: [&str; NFEATURES] = [
+    "win", "block_win", "three", "block_three", "two", "centre",
+    "north", "east", "south", "west",
+];
+
+pub type Features = [f64; NFEATURES];
+
+pub fn features(b: &Board, m: LegalMove) -> Features {
+    let (row, col) = m.target();
+    let own = b.line_length(row, col, b.active());
+    let opp = b.line_length(row, col, b.active().flip());
+    let mid = (b.size() - 1) as f64 / 2.0;
+    let dist = (row as f64 - mid).abs().max((col as f64 - mid).abs()) / mid;
+    let mut f = [0.0; NFEATURES];
+    f[0] = if m.is_winning() { 1.0 } else { 0.0 };
+    f[1] = if opp >= 4 { 1.0 } else { 0.0 };
+    f[2] = if own == 3 { 1.0 } else { 0.0 };
+    f[3] = if opp == 3 { 1.0 } else { 0.0 };
+    f[4] = if own == 2 { 1.0 } else { 0.0 };
+    f[5] = 1.0 - dist;
+    f[6 + m.base().side().index()] = 1.0;
+    f
+}
+
+fn dot(w: &Features, f: &Features) -> f64 {
+    w.iter().zip(f).map(|(w, f)| w * f).sum()
+}
+
+// Softmax in place, shifted by the maximum for stability.
+fn softmax(xs: &mut [f64]) {
+    let max = xs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut sum = 0.0;
+    for x in xs.iter_mut() {
+        *x = (*x - max).exp();
+        sum += *x;
+    }
+    for x in xs.iter_mut() { *x /= sum; }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy {
+    weights: Features,
+}
+
+impl Policy {
+    pub fn new(weights: Features) -> Policy {
+        Policy { weights }
+    }
+
+    pub fn weights(&self) -> &Features { &self.weights }
+
+    pub fn probabilities(&self, b: &Board) -> Vec<(LegalMove, f64)> {
+        let moves = b.legal_moves_iter().collect::<Vec<_>>();
+        let mut ps = moves.iter().map(|&m| dot(&self.weights, &features(b, m)))
+            .collect::<Vec<_>>();
+        softmax(&mut ps);
+        moves.into_iter().zip(ps).collect()
+    }
+
+    // Takes a winning move when there is one, like the uniform rollout.
+    // Returns `None` if there are no legal moves.
+    pub fn choose<R: Rng>(&self, b: &Board, rng: &mut R) -> Option<LegalMove> {
+        if let Some(m) = b.legal_moves_iter().find(|m| m.is_winning()) {
+            return Some(m);
+        }
+        let ps = self.probabilities(b);
+        let mut x = rng.gen::<f64>();
+        for &(m, p) in &ps {
+            if x < p { return Some(m); }
+            x -= p;
+        }
+        ps.last().map(|&(m, _)| m)
+    }
+
+    pub fn read<R: BufRead>(r: R) -> io::Result<Policy> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut weights = [0.0; NFEATURES];
+        for line in r.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap();
+            let i = FEATURES.iter().position(|&f| f == name)
+                .ok_or_else(|| invalid("unknown feature"))?;
+            weights[i] = words.next().and_then(|w| w.parse().ok())
+                .ok_or_else(|| invalid("invalid weight"))?;
+        }
+        Ok(Policy { weights })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Policy> {
+        Policy::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        write!(File::create(path)?, "{}", self)
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy { weights: [0.0; NFEATURES] }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, w) in FEATURES.iter().zip(&self.weights) {
+            writeln!(f, "{} {}", name, w)?;
+        }
+        Ok(())
+    }
+}
+
+struct Example {
+    features: Vec<Features>,
+    target: Vec<f64>,
+}
+
+// The target is the share of root playouts each legal move received. A
+// solved root reports only its chosen move, which then gets all the weight.
+fn example(sample: &Sample) -> Option<Example> {
+    let b = &sample.board;
+    let moves = b.legal_moves_iter().collect::<Vec<_>>();
+    let mut target = vec![0.0; moves.len()];
+    for c in &sample.children {
+        if let Some(i) = moves.iter().position(|m| m.base() == c.m.base()) {
+            target[i] = c.visits as f64;
+        }
+    }
+    let total = target.iter().sum::<f64>();
+    if total <= 0.0 { return None; }
+    for t in &mut target { *t /= total; }
+    let features = moves.iter().map(|&m| features(b, m)).collect();
+    Some(Example { features, target })
+}
+
+pub struct Trainer {
+    pub epochs: usize,
+    pub rate: f64,
+    pub l2: f64,
+}
+
+impl Trainer {
+    pub fn new() -> Trainer {
+        Trainer { epochs: 200, rate: 0.5, l2: 1e-4 }
+    }
+
+    // Full-batch gradient descent from zero weights. `on_epoch` gets the
+    // epoch number and the mean loss before that epoch's update.
+    pub fn train<F: FnMut(usize, f64)>(&self, samples: &[Sample], mut on_epoch: F) -> Policy {
+        let examples = samples.iter().filter_map(example).collect::<Vec<_>>();
+        let mut weights = [0.0; NFEATURES];
+        if examples.is_empty() { return Policy::new(weights); }
+        for epoch in 0..self.epochs {
+            let mut grad = [0.0; NFEATURES];
+            let mut loss = 0.0;
+            for ex in &examples {
+                let mut ps = ex.features.iter().map(|f| dot(&weights, f)).collect::<Vec<_>>();
+                softmax(&mut ps);
+                for ((f, &p), &t) in ex.features.iter().zip(&ps).zip(&ex.target) {
+                    if t > 0.0 { loss -= t * p.max(1e-12).ln(); }
+                    for (g, x) in grad.iter_mut().zip(f) { *g += (p - t) * x; }
+                }
+            }
+            let n = examples.len() as f64;
+            on_epoch(epoch, loss / n);
+            for (w, g) in weights.iter_mut().zip(&grad) {
+                *w -= self.rate * (g / n + self.l2 * *w);
+            }
+        }
+        Policy::new(weights)
+    }
+
+    pub fn loss(policy: &Policy, samples: &[Sample]) -> f64 {
+        let examples = samples.iter().filter_map(example).collect::<Vec<_>>();
+        let mut loss = 0.0;
+        for ex in &examples {
+            let mut ps = ex.features.iter().map(|f| dot(&policy.weights, f)).collect::<Vec<_>>();
+            softmax(&mut ps);
+            for (&p, &t) in ps.iter().zip(&ex.target) {
+                if t > 0.0 { loss -= t * p.max(1e-12).ln(); }
+            }
+        }
+        loss / examples.len().max(1) as f64
+    }
+}
+
+impl Default for Trainer {
+    fn default() -> Trainer { Trainer::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+    use player::{Budget, MCTSPlayer};
+    use selfplay::{self, Config};
+
+    #[test]
+    fn policy_features() {
+        let b = "x3/x3/4/4 x".parse::<Board>().unwrap();
+        let m = b.parse_move("S0").unwrap();
+        let f = features(&b, m);
+        assert_eq!(&[0.0, 0.0, 1.0, 0.0, 0.0], &f[..5]);
+        assert_eq!(&[0.0, 0.0, 1.0, 0.0], &f[6..]);
+        let b = "x3/x3/4/4 o".parse::<Board>().unwrap();
+        let f = features(&b, b.parse_move("S0").unwrap());
+        assert_eq!(&[0.0, 0.0, 0.0, 1.0, 0.0], &f[..5]);
+    }
+
+    #[test]
+    fn policy_round_trip() {
+        let policy = Policy::new([1.5, -2.0, 0.25, 0.0, 3.0, -1.0, 0.5, 0.5, 0.0, -0.125]);
+        let policy1 = Policy::read(policy.to_string().as_bytes()).unwrap();
+        assert_eq!(policy, policy1);
+        assert!(Policy::read("bogus 1.0\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn trainer_reduces_loss() {
+        let mut rng = StdRng::from_seed(&[7]);
+        let player = MCTSPlayer::with_budget(Budget::Iterations(200));
+        let config = Config { size: 6, blocks: 3, random_plies: 2 };
+        let mut samples = Vec::new();
+        for _ in 0..4 {
+            samples.extend(selfplay::play_game(&player, &config, &mut rng));
+        }
+        let trainer = Trainer { epochs: 50, ..Trainer::new() };
+        let mut losses = Vec::new();
+        let policy = trainer.train(&samples, |_, loss| losses.push(loss));
+        assert_eq!(50, losses.len());
+        assert!(Trainer::loss(&policy, &samples) < losses[0]);
+        let mut mcts = MCTSPlayer::with_budget(Budget::Iterations(100));
+        mcts.set_rollout_policy(policy);
+        let b = Board::generate_with_rng(&mut rng, 6, 3);
+        mcts.search(&b);
+    }
+}
@@ -0,0 +1,189 @@
+// A line-based engine protocol in the spirit of GTP. Each command line gets
+// exactly one response line: `= [result]` on success or `? message` on
+// failure.
+//
+//   protocol_version        = 1
+//   name                    = zgc4
+//   version                 = <crate version>
+//   boardsize N             start over on an empty N×N board
+//   block ROW COL           add a block to the starting position
+//   position POS            start over from a position in board notation
+//   reset                   take back all moves
+//   play MOVE               = ongoing|won|drawn
+//   genmove [MS]            play a book move, or search; return the chosen move
+//   genmove playouts N      the same, searching N playouts
+//   info                    = statistics of the last search
+//   undo                    take back the last move
+//   moves                   = legal moves, space-separated
+//   showboard               = current position in board notation
+//   quit                    = and exit
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use board::{self, Board, Entry, GameState, LegalMove};
+use player::{Budget, MCTSPlayer, SearchInfo};
+
+pub const VERSION: usize = 1;
+
+pub type Response = Result<String, String>;
+
+pub struct Engine {
+    budget: Budget,
+    player: MCTSPlayer,
+    initial: Board,
+    board: Board,
+    history: Vec<LegalMove>,
+    info: Option<SearchInfo>,
+    quit: bool,
+}
+
+fn message(e: board::Error) -> String {
+    let s = e.to_string();
+    s.trim_start_matches("Error: ").to_string()
+}
+
+fn state_name(state: GameState) -> &'static str {
+    match state {
+        GameState::Ongoing => "ongoing",
+        GameState::Won => "won",
+        GameState::Drawn => "drawn",
+    }
+}
+
+fn arg<T: ::std::str::FromStr>(args: &[&str], i: usize) -> Result<T, String> {
+    let arg = args.get(i).ok_or_else(|| String::from("missing argument"))?;
+    arg.parse().map_err(|_| format!("invalid argument `{}`", arg))
+}
+
+impl Engine {
+    // `genmove` searches with `player`, whose budget is the default.
+    pub fn new(player: MCTSPlayer) -> Engine {
+        let initial = Board::new(10);
+        let board = initial.clone();
+        Engine {
+            budget: player.budget(),
+            player,
+            initial,
+            board,
+            history: Vec::new(),
+            info: None,
+            quit: false,
+        }
+    }
+
+    pub fn board(&self) -> &Board { &self.board }
+
+    pub fn history(&self) -> &[LegalMove] { &self.history }
+
+    pub fn info(&self) -> Option<SearchInfo> { self.info }
+
+    pub fn is_quit(&self) -> bool { self.quit }
+
+    fn setup(&mut self, b: Board) -> Response {
+        self.initial = b.clone();
+        self.board = b;
+        self.history.clear();
+        self.info = None;
+        Ok(String::new())
+    }
+
+    fn ensure_ongoing(&self) -> Result<(), String> {
+        match self.board.state() {
+            GameState::Ongoing => Ok(()),
+            _ => Err(String::from("game over")),
+        }
+    }
+
+    fn play(&mut self, m: LegalMove) -> Response {
+        let state = self.board.make_legal_move(m);
+        self.history.push(m);
+        Ok(String::from(state_name(state)))
+    }
+
+    pub fn execute(&mut self, line: &str) -> Response {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Err(String::from("empty command")),
+        };
+        match command {
+            "protocol_version" => Ok(VERSION.to_string()),
+            "name" => Ok(String::from("zgc4")),
+            "version" => Ok(String::from(env!("CARGO_PKG_VERSION"))),
+            "boardsize" => {
+                let size = arg::<usize>(args, 0)?;
+                if size < 4 { return Err(String::from("board size must be at least 4")); }
+                self.setup(Board::new(size))
+            },
+            "block" => {
+                let (row, col) = (arg::<usize>(args, 0)?, arg::<usize>(args, 1)?);
+                if !self.history.is_empty() { return Err(String::from("moves already played")); }
+                // `place` works out the state afresh, so closing the last
+                // entry point leaves the game drawn.
+                let mut b = self.initial.clone();
+                b.place(row, col, Entry::Block).map_err(message)?;
+                self.setup(b)
+            },
+            "position" => {
+                let b = args.join(" ").parse::<Board>().map_err(message)?;
+                self.setup(b)
+            },
+            "reset" => {
+                let b = self.initial.clone();
+                self.setup(b)
+            },
+            "play" => {
+                self.ensure_ongoing()?;
+                let m = self.board.parse_move(&arg::<String>(args, 0)?).map_err(message)?;
+                self.play(m)
+            },
+            "genmove" => {
+                self.ensure_ongoing()?;
+                let budget = match args.first() {
+                    Some(&"playouts") => Budget::Iterations(arg(args, 1)?),
+                    Some(..) => Budget::Time(Duration::from_millis(arg(args, 0)?)),
+                    None => self.budget,
+                };
+                self.player.set_budget(budget);
+                let m = self.player.think(&self.board)
+                    .ok_or_else(|| String::from("no legal moves"))?;
+                self.info = self.player.last();
+                self.play(m)?;
+                Ok(m.to_string())
+            },
+            "info" => self.info.map(|info| info.to_string())
+                .ok_or_else(|| String::from("no search yet")),
+            "undo" => {
+                if self.history.pop().is_none() { return Err(String::from("no moves")); }
+                let mut b = self.initial.clone();
+                for &m in &self.history { b.make_legal_move(m); }
+                self.board = b;
+                Ok(String::new())
+            },
+            "moves" => Ok(self.board.legal_moves_iter().map(|m| m.to_string())
+                          .collect::<Vec<_>>().join(" ")),
+            "showboard" => Ok(self.board.to_notation()),
+            "quit" => {
+                self.quit = true;
+                Ok(String::new())
+            },
+            _ => Err(format!("unknown command `{}`", command)),
+        }
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') { continue; }
+            match self.execute(&line) {
+                Ok(ref s) if s.is_empty() => writeln!(output, "=")?,
+                Ok(s) => writeln!(output, "= {}", s)?,
+                Err(e) => writeln!(output, "? {}", e)?,
+            }
+            output.flush()?;
+            if self.quit { break; }
+        }
+        Ok(())
+    }
+}
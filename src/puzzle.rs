@@ -0,0 +1,209 @@
+// Tactical puzzles: positions where the side to move can force a win in
+// exactly N of its own moves, and only one first move does it. They are
+// found along random or self-play games and proved with an exact search.
+// A puzzle is written as the position in board notation followed by the
+// solution and N, e.g. `x3/x3/x3/4 x S0 1`.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::io::BufRead;
+use std::str;
+
+use rand::Rng;
+
+use board::{Board, GameState, LegalMove};
+use book;
+use player::{self, MCTSPlayer};
+
+// Whether the side to move can force a win within `n` of its moves.
+pub fn wins_within(b: &Board, n: usize) -> bool {
+    if n == 0 || b.state() != GameState::Ongoing { return false; }
+    let moves = b.legal_moves_iter().collect::<Vec<_>>();
+    if moves.iter().any(|m| m.is_winning()) { return true; }
+    n > 1 && moves.iter().any(|&m| forces_win(b, m, n))
+}
+
+// Whether playing `m` leaves the opponent lost within `n - 1` more moves.
+fn forces_win(b: &Board, m: LegalMove, n: usize) -> bool {
+    if m.is_winning() { return true; }
+    let mut b1 = b.clone();
+    b1.make_legal_move(m);
+    n > 1 && b1.state() == GameState::Ongoing && loses_within(&b1, n - 1)
+}
+
+// Whether every move of the side to move lets the opponent win within `n`.
+fn loses_within(b: &Board, n: usize) -> bool {
+    let moves = b.legal_moves_iter().collect::<Vec<_>>();
+    if moves.iter().any(|m| m.is_winning()) { return false; }
+    moves.iter().all(|&m| {
+        let mut b1 = b.clone();
+        b1.make_legal_move(m);
+        b1.state() == GameState::Ongoing && wins_within(&b1, n)
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub board: Board,
+    pub depth: usize,
+    pub solution: LegalMove,
+}
+
+impl Puzzle {
+    // Moves from different sides that land on the same cell are the same
+    // move, so uniqueness is judged by landing cell.
+    pub fn find(b: &Board, depth: usize) -> Option<Puzzle> {
+        if depth == 0 || b.state() != GameState::Ongoing || wins_within(b, depth - 1) {
+            return None;
+        }
+        let mut solution: Option<LegalMove> = None;
+        for m in b.legal_moves_iter() {
+            if !forces_win(b, m, depth) { continue; }
+            match solution {
+                Some(s) if s.target() != m.target() => return None,
+                Some(..) => (),
+                None => solution = Some(m),
+            }
+        }
+        solution.map(|solution| Puzzle { board: b.clone(), depth, solution })
+    }
+
+    pub fn is_solved_by(&self, m: LegalMove) -> bool {
+        m.target() == self.solution.target()
+    }
+}
+
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.board.to_notation(), self.solution, self.depth)
+    }
+}
+
+impl str::FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Puzzle, String> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        if words.len() != 4 { return Err(String::from("expected `position move depth`")); }
+        let board = format!("{} {}", words[0], words[1]).parse::<Board>()
+            .map_err(|e| e.to_string())?;
+        let solution = board.parse_move(words[2]).map_err(|e| e.to_string())?;
+        let depth = words[3].parse().map_err(|_| format!("invalid depth `{}`", words[3]))?;
+        Ok(Puzzle { board, depth, solution })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub size: usize,
+    pub blocks: usize,
+    pub depth: usize,
+}
+
+// Plays one game on a fresh layout, by `player` or at random, and returns
+// the puzzles met along the way.
+pub fn from_game<R: Rng>(player: Option<&MCTSPlayer>, config: &Config, rng: &mut R)
+                         -> Vec<Puzzle> {
+    let mut b = Board::generate_with_rng(rng, config.size, config.blocks);
+    let mut puzzles = Vec::new();
+    while b.state() == GameState::Ongoing {
+        if let Some(puzzle) = Puzzle::find(&b, config.depth) { puzzles.push(puzzle); }
+        let m = match player {
+            Some(player) => player.search(&b).m,
+            None => match player::choose_winning_or_random(&b, rng) {
+                Some(m) => m,
+                None => break,
+            },
+        };
+        b.make_legal_move(m);
+    }
+    puzzles
+}
+
+// Plays up to `games` games until `count` puzzles are found, keeping only
+// the first puzzle of each game, as later ones tend to be the same attack,
+// and none that is a rotation or reflection of one already found.
+pub fn generate<R: Rng>(player: Option<&MCTSPlayer>, config: &Config, games: usize,
+                        count: usize, rng: &mut R) -> Vec<Puzzle> {
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for _ in 0..games {
+        if found.len() >= count { break; }
+        let puzzles = from_game(player, config, rng);
+        if let Some(puzzle) = puzzles.into_iter()
+            .find(|p| seen.insert(book::canonical(&p.board).0)) {
+            found.push(puzzle);
+        }
+    }
+    found
+}
+
+// Reads puzzles one per line, skipping blank lines. There are no comments,
+// as a position may itself start with `#`.
+pub fn read<R: BufRead>(r: R) -> Result<Vec<Puzzle>, String> {
+    let mut puzzles = Vec::new();
+    for (i, line) in r.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() { continue; }
+        puzzles.push(line.parse::<Puzzle>().map_err(|e| format!("line {}: {}", i + 1, e))?);
+    }
+    Ok(puzzles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    #[test]
+    fn puzzle_win_in_one() {
+        let b = "x3/x3/x3/4 x".parse::<Board>().unwrap();
+        assert!(wins_within(&b, 1));
+        let puzzle = Puzzle::find(&b, 1).unwrap();
+        assert_eq!((3, 0), puzzle.solution.target());
+        assert!(Puzzle::find(&b, 2).is_none());
+        let b = "x3/x3/x3/4 o".parse::<Board>().unwrap();
+        assert!(!wins_within(&b, 1));
+        assert!(Puzzle::find(&b, 1).is_none());
+    }
+
+    #[test]
+    fn puzzle_generate() {
+        let mut rng = StdRng::from_seed(&[11]);
+        let config = Config { size: 5, blocks: 2, depth: 2 };
+        let mut puzzles = Vec::new();
+        for _ in 0..20 {
+            puzzles.extend(from_game(None, &config, &mut rng));
+            if puzzles.len() >= 3 { break; }
+        }
+        assert!(!puzzles.is_empty());
+        for puzzle in &puzzles {
+            assert!(wins_within(&puzzle.board, 2));
+            assert!(!wins_within(&puzzle.board, 1));
+            let mut b = puzzle.board.clone();
+            b.make_legal_move(puzzle.solution);
+            assert!(loses_within(&b, 1));
+            let parsed = puzzle.to_string().parse::<Puzzle>().unwrap();
+            assert_eq!(puzzle.to_string(), parsed.to_string());
+        }
+        assert!("4/4/4/4 x N0".parse::<Puzzle>().is_err());
+    }
+
+    #[test]
+    fn puzzle_generate_distinct() {
+        let mut rng = StdRng::from_seed(&[11]);
+        let config = Config { size: 5, blocks: 2, depth: 1 };
+        let puzzles = generate(None, &config, 50, 5, &mut rng);
+        assert!(!puzzles.is_empty() && puzzles.len() <= 5);
+        let mut hashes = puzzles.iter().map(|p| book::canonical(&p.board).0).collect::<Vec<_>>();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(puzzles.len(), hashes.len());
+        let text = puzzles.iter().map(|p| format!("{}\n", p)).collect::<String>();
+        let parsed = read(format!("\n#3/4/4/4 x N1 1\n{}", text).as_bytes()).unwrap();
+        let text = format!("#3/4/4/4 x N1 1\n{}", text);
+        assert_eq!(text, parsed.iter().map(|p| format!("{}\n", p)).collect::<String>());
+        assert_eq!(Err(String::from("line 2: expected `position move depth`")),
+                   read("x3/x3/x3/4 x S0 1\n4/4/4/4 x N0\n".as_bytes()).map(|p| p.len()));
+    }
+}
@@ -0,0 +1,332 @@
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::slice;
+use std::str;
+
+use board::{self, Board, Entry, Move, Outcome};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Board(board::Error),
+    Syntax(usize),
+    WrongResult,
+}
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "Error: {}", e),
+            Error::Board(ref e) => e.fmt(f),
+            Error::Syntax(line) => write!(f, "Error: line {}: syntax error", line),
+            Error::WrongResult => write!(f, "Error: result does not match the final position"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(..) => "I/O error",
+            Error::Board(..) => "Board error",
+            Error::Syntax(..) => "Syntax error",
+            Error::WrongResult => "Result does not match the final position",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Board(ref e) => Some(e),
+            Error::Syntax(..) => None,
+            Error::WrongResult => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error { Error::Io(e) }
+}
+
+impl From<board::Error> for Error {
+    fn from(e: board::Error) -> Error { Error::Board(e) }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ply {
+    pub m: Move,
+    pub info: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    players: [String; 2],
+    initial: Board,
+    moves: Vec<Ply>,
+    result: Option<Outcome>,
+}
+
+impl Record {
+    pub fn new(initial: Board) -> Record {
+        let players = [String::from("?"), String::from("?")];
+        Record { players, initial, moves: Vec::new(), result: None }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Record> {
+        let mut s = String::new();
+        File::open(path)?.read_to_string(&mut s)?;
+        s.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut f = File::create(path)?;
+        write!(f, "{}", self)?;
+        Ok(())
+    }
+
+    pub fn player(&self, entry: Entry) -> Option<&str> {
+        entry.seat().map(|i| &self.players[i][..])
+    }
+
+    // Names given for entries that are not players are ignored.
+    pub fn set_player(&mut self, entry: Entry, name: &str) {
+        if let Some(i) = entry.seat() { self.players[i] = String::from(name); }
+    }
+
+    pub fn initial(&self) -> &Board { &self.initial }
+
+    pub fn moves(&self) -> &[Ply] { &self.moves }
+
+    pub fn push(&mut self, m: Move, info: Option<String>) {
+        self.moves.push(Ply { m, info });
+    }
+
+    pub fn result(&self) -> Option<Outcome> { self.result }
+
+    pub fn set_result(&mut self, result: Option<Outcome>) {
+        self.result = result;
+    }
+
+    pub fn replay(&self) -> Replay<'_> {
+        Replay { board: Some(self.initial.clone()), moves: self.moves.iter() }
+    }
+
+    pub fn final_board(&self) -> board::Result<Board> {
+        self.replay().last().unwrap_or_else(|| Ok(self.initial.clone()))
+    }
+}
+
+// Yields the board after each recorded move, stopping at the first illegal
+// one.
+pub struct Replay<'a> {
+    board: Option<Board>,
+    moves: slice::Iter<'a, Ply>,
+}
+
+impl<'a> Iterator for Replay<'a> {
+    type Item = board::Result<Board>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ply = self.moves.next()?;
+        let mut b = self.board.take()?;
+        match b.make_move(ply.m) {
+            Ok(..) => {
+                self.board = Some(b.clone());
+                Some(Ok(b))
+            },
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn write_tag(f: &mut fmt::Formatter, key: &str, value: &str) -> fmt::Result {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(f, "[{} \"{}\"]", key, value)
+}
+
+fn result_token(result: Option<Outcome>) -> String {
+    result.map_or_else(|| String::from("*"), |r| r.to_string())
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_tag(f, "Player1", &self.players[0])?;
+        write_tag(f, "Player2", &self.players[1])?;
+        write_tag(f, "Position", &self.initial.to_notation())?;
+        write_tag(f, "Result", &result_token(self.result))?;
+        writeln!(f)?;
+        for (i, ply) in self.moves.iter().enumerate() {
+            if i % 2 == 0 {
+                if i > 0 { writeln!(f)?; }
+                write!(f, "{}.", i / 2 + 1)?;
+            }
+            write!(f, " {}", ply.m)?;
+            if let Some(ref info) = ply.info {
+                let info = info.replace(['{', '}'], "").replace('\n', " ");
+                write!(f, " {{{}}}", info)?;
+            }
+        }
+        if !self.moves.is_empty() { writeln!(f)?; }
+        writeln!(f, "{}", result_token(self.result))
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(&str, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, rest) = inner.split_at(inner.find(' ')?);
+    let rest = rest.trim_start().strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return None,
+            c => value.push(c),
+        }
+    }
+    Some((key, value))
+}
+
+fn parse_result(token: &str) -> Option<Option<Outcome>> {
+    match token {
+        "*" => Some(None),
+        _ => token.parse().ok().map(Some),
+    }
+}
+
+impl str::FromStr for Record {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Record> {
+        let mut players = [String::from("?"), String::from("?")];
+        let mut initial = None;
+        let mut result = None;
+        let mut moves = Vec::<Ply>::new();
+        let mut done = false;
+        for (i, line) in s.lines().enumerate() {
+            let lineno = i + 1;
+            let line = line.trim();
+            if line.is_empty() { continue; }
+            if done { return Err(Error::Syntax(lineno)); }
+            if line.starts_with('[') {
+                if !moves.is_empty() { return Err(Error::Syntax(lineno)); }
+                let (key, value) = parse_tag(line).ok_or(Error::Syntax(lineno))?;
+                match key {
+                    "Player1" => players[0] = value,
+                    "Player2" => players[1] = value,
+                    "Position" => initial = Some(value.parse::<Board>()?),
+                    "Result" => result = parse_result(&value).ok_or(Error::Syntax(lineno))?,
+                    _ => (),
+                }
+                continue;
+            }
+            let mut rest = line;
+            while !rest.is_empty() {
+                if done { return Err(Error::Syntax(lineno)); }
+                if let Some(comment) = rest.strip_prefix('{') {
+                    let end = comment.find('}').ok_or(Error::Syntax(lineno))?;
+                    let ply = moves.last_mut().ok_or(Error::Syntax(lineno))?;
+                    ply.info = Some(String::from(&comment[..end]));
+                    rest = comment[end + 1..].trim_start();
+                    continue;
+                }
+                let end = rest.find(|c: char| c.is_whitespace() || c == '{')
+                    .unwrap_or(rest.len());
+                let token = &rest[..end];
+                rest = rest[end..].trim_start();
+                if let Some(n) = token.strip_suffix('.') {
+                    if n.parse::<usize>().is_err() { return Err(Error::Syntax(lineno)); }
+                } else if let Some(r) = parse_result(token) {
+                    if r != result { return Err(Error::Syntax(lineno)); }
+                    done = true;
+                } else {
+                    moves.push(Ply { m: token.parse()?, info: None });
+                }
+            }
+        }
+        let initial = initial.ok_or(Error::Syntax(1))?;
+        let record = Record { players, initial, moves, result };
+        // A game can end early by resignation or agreement, but one that
+        // ended on the board must carry that result.
+        let outcome = record.final_board()?.outcome();
+        if outcome.is_some() && outcome != result { return Err(Error::WrongResult); }
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::Side;
+
+    fn sample() -> Record {
+        let mut b = Board::new(4);
+        b.set(1, 1, Entry::Block).unwrap();
+        let mut game = Record::new(b);
+        game.set_player(Entry::Player1, "Human \"Bob\"");
+        game.set_player(Entry::Player2, "MCTS");
+        game.push(Move::new(Side::North, 0), None);
+        game.push(Move::new(Side::West, 2), Some(String::from("E[S] = 0.40")));
+        game.push(Move::new(Side::North, 0), None);
+        game
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut game = sample();
+        game.set_result(Some(Outcome::Win(Entry::Player2)));
+        let s = game.to_string();
+        let game1 = s.parse::<Record>().unwrap();
+        assert_eq!(s, game1.to_string());
+        assert_eq!(Some("Human \"Bob\""), game1.player(Entry::Player1));
+        assert_eq!(None, game1.player(Entry::Empty));
+        assert_eq!(game.moves(), game1.moves());
+        assert_eq!(Some(Outcome::Win(Entry::Player2)), game1.result());
+    }
+
+    #[test]
+    fn record_replay() {
+        let game = sample();
+        let boards = game.replay().collect::<board::Result<Vec<_>>>().unwrap();
+        assert_eq!(3, boards.len());
+        assert_eq!("4/1#2/3o/x3 x", boards[1].to_notation());
+        assert_eq!("4/1#2/x2o/x3 o", game.final_board().unwrap().to_notation());
+    }
+
+    #[test]
+    fn record_parse_errors() {
+        let position = "[Position \"4/4/4/4 x\"]\n";
+        assert!(format!("{}\n1. N0 N1 *\n", position).parse::<Record>().is_ok());
+        match "1. N0 *\n".parse::<Record>() { Err(Error::Syntax(1)) => (), r => panic!("{:?}", r) }
+        match format!("{}\n1. N0 1-0\n", position).parse::<Record>() {
+            Err(Error::Syntax(3)) => (),
+            r => panic!("{:?}", r),
+        }
+        match format!("{}\n1. N9 *\n", position).parse::<Record>() {
+            Err(Error::Board(board::Error::OutOfBounds)) => (),
+            r => panic!("{:?}", r),
+        }
+        match format!("{}\n1. N0 N0 N0 N0 N0 *\n", position).parse::<Record>() {
+            Err(Error::Board(board::Error::IllegalMove(..))) => (),
+            r => panic!("{:?}", r),
+        }
+        let won = |moves: &str, result: &str| {
+            format!("[Position \"x3/x3/x3/4 x\"]\n[Result \"{1}\"]\n\n1. {0} {1}\n", moves, result)
+                .parse::<Record>()
+        };
+        assert!(won("S0", "1-0").is_ok());
+        for result in &["0-1", "1/2-1/2", "*"] {
+            match won("S0", result) { Err(Error::WrongResult) => (), r => panic!("{:?}", r) }
+        }
+        assert!(won("N1", "0-1").is_ok());
+        match format!("{}\n1. Q0 *\n", position).parse::<Record>() {
+            Err(Error::Board(board::Error::InvalidSide)) => (),
+            r => panic!("{:?}", r),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+// Self-play training data. A file is `MAGIC` followed by samples, each
+// encoded as:
+//
+//   u8       board size N
+//   [u8]     N*N cells, four to a byte, two bits each (empty, block, P1, P2)
+//   u8       side to move (0 for P1, 1 for P2)
+//   u8       final result for the side to move (0 loss, 1 draw, 2 win)
+//   u16      number of children, then for each:
+//     u8     move side (N, E, S, W)
+//     u8     move position
+//     f32    expected score of the move for the side to move
+//     u32    playouts through the move
+//
+// Multi-byte values are little-endian.
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use rand::Rng;
+
+use board::{Board, Entry, GameState, Move, Side};
+use player::{self, ChildStats, MCTSPlayer};
+
+pub const MAGIC: &[u8; 8] = b"ZGC4SP01";
+
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub board: Board,
+    pub children: Vec<ChildStats>,
+    pub result: f64,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{} too large for the format", what))
+}
+
+fn entry_code(entry: Entry) -> u8 {
+    match entry {
+        Entry::Empty => 0,
+        Entry::Block => 1,
+        Entry::Player1 => 2,
+        Entry::Player2 => 3,
+    }
+}
+
+impl Sample {
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let b = &self.board;
+        let mut buf = vec![u8::try_from(b.size()).map_err(|_| too_large("board size"))?];
+        for cells in b.entries().chunks(4) {
+            buf.push(cells.iter().enumerate()
+                     .fold(0, |byte, (i, &e)| byte | (entry_code(e) << (2 * i))));
+        }
+        buf.push(if b.active() == Entry::Player1 { 0 } else { 1 });
+        buf.push((self.result * 2.0).round() as u8);
+        let nchildren = u16::try_from(self.children.len()).map_err(|_| too_large("move count"))?;
+        buf.extend_from_slice(&nchildren.to_le_bytes());
+        for c in &self.children {
+            let m = c.m.base();
+            buf.push(m.side().index() as u8);
+            buf.push(u8::try_from(m.pos()).map_err(|_| too_large("move position"))?);
+            buf.extend_from_slice(&(c.expected as f32).to_le_bytes());
+            let visits = u32::try_from(c.visits).map_err(|_| too_large("visit count"))?;
+            buf.extend_from_slice(&visits.to_le_bytes());
+        }
+        w.write_all(&buf)
+    }
+
+    // Returns `None` at a clean end of input.
+    pub fn read<R: Read>(r: &mut R) -> io::Result<Option<Sample>> {
+        let mut size = [0u8; 1];
+        if r.read(&mut size)? == 0 { return Ok(None); }
+        let size = size[0] as usize;
+        let mut cells = vec![0u8; (size * size).div_ceil(4)];
+        r.read_exact(&mut cells)?;
+        let entries = (0..size * size).map(|i| {
+            match (cells[i / 4] >> (2 * (i % 4))) & 3 {
+                0 => Entry::Empty,
+                1 => Entry::Block,
+                2 => Entry::Player1,
+                _ => Entry::Player2,
+            }
+        }).collect::<Vec<_>>();
+        let mut header = [0u8; 4];
+        r.read_exact(&mut header)?;
+        let active = if header[0] == 0 { Entry::Player1 } else { Entry::Player2 };
+        let board = Board::from_entries(size, &entries, active)
+            .map_err(|_| invalid("invalid board"))?;
+        if header[1] > 2 { return Err(invalid("invalid result")); }
+        let result = f64::from(header[1]) / 2.0;
+        let nchildren = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let mut children = Vec::with_capacity(nchildren);
+        for _ in 0..nchildren {
+            let mut child = [0u8; 10];
+            r.read_exact(&mut child)?;
+            let side = Side::from_index(child[0] as usize)
+                .ok_or_else(|| invalid("invalid side"))?;
+            let m = Move::new(side, child[1] as usize).annotated(&board)
+                .ok_or_else(|| invalid("illegal move"))?;
+            let expected = f64::from(f32::from_le_bytes([child[2], child[3], child[4], child[5]]));
+            let visits = u32::from_le_bytes([child[6], child[7], child[8], child[9]]) as usize;
+            children.push(ChildStats { m, expected, visits });
+        }
+        Ok(Some(Sample { board, children, result }))
+    }
+}
+
+pub fn write_header<W: Write>(w: &mut W) -> io::Result<()> {
+    w.write_all(MAGIC)
+}
+
+pub struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(mut inner: R) -> io::Result<Reader<R>> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != MAGIC { return Err(invalid("not a self-play data file")); }
+        Ok(Reader { inner })
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Sample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Sample::read(&mut self.inner).transpose()
+    }
+}
+
+// Reads every sample in a training data file.
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Sample>> {
+    Reader::new(BufReader::new(File::open(path)?))?.collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub size: usize,
+    pub blocks: usize,
+    // Opening plies played at random, without samples, for variety.
+    pub random_plies: usize,
+}
+
+pub fn play_game<R: Rng>(player: &MCTSPlayer, config: &Config, rng: &mut R) -> Vec<Sample> {
+    let mut b = Board::generate_with_rng(rng, config.size, config.blocks);
+    let mut samples = Vec::new();
+    let mut ply = 0;
+    while b.state() == GameState::Ongoing {
+        let m = if ply < config.random_plies {
+            match player::choose_winning_or_random(&b, rng) {
+                Some(m) => m,
+                None => break,
+            }
+        } else {
+            let (info, children) = player.analyse(&b);
+            samples.push(Sample { board: b.clone(), children, result: 0.5 });
+            info.m
+        };
+        ply += 1;
+        b.make_legal_move(m);
+    }
+    if let Some(winner) = b.winner() {
+        for sample in &mut samples {
+            sample.result = if sample.board.active() == winner { 1.0 } else { 0.0 };
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+    use player::Budget;
+
+    #[test]
+    fn sample_round_trip() {
+        let board = "x3/1#2/4/3o o".parse::<Board>().unwrap();
+        let children = board.legal_moves_iter().enumerate().map(|(i, m)| {
+            ChildStats { m, expected: i as f64 / 16.0, visits: i * 1000 }
+        }).collect::<Vec<_>>();
+        let sample = Sample { board, children, result: 0.5 };
+        let mut buf = Vec::new();
+        write_header(&mut buf).unwrap();
+        sample.write(&mut buf).unwrap();
+        sample.write(&mut buf).unwrap();
+        let samples = Reader::new(&buf[..]).unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(2, samples.len());
+        assert_eq!(sample.board.to_notation(), samples[1].board.to_notation());
+        assert_eq!(sample.children, samples[1].children);
+        assert_eq!(0.5, samples[1].result);
+        assert!(Reader::new(&b"ZGC4XX01"[..]).is_err());
+        assert!(Reader::new(&buf[..buf.len() - 1]).unwrap().nth(1).unwrap().is_err());
+        let mut sample = sample;
+        sample.children[0].visits = 1 << 32;
+        let e = sample.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        let sample = Sample { board: Board::new(256), children: Vec::new(), result: 0.5 };
+        assert_eq!(io::ErrorKind::InvalidInput, sample.write(&mut Vec::new()).unwrap_err().kind());
+    }
+
+    #[test]
+    fn sample_proven_children() {
+        // Every move but S0 and E4 lets o win at once, which the search
+        // proves; those moves still report the playouts spent on them.
+        let b = "x4/o4/o4/o4/5 x".parse::<Board>().unwrap();
+        let player = MCTSPlayer::with_budget(Budget::Iterations(2000));
+        let (_, children) = player.analyse(&b);
+        let lost = children.iter().filter(|c| c.expected == 0.0).collect::<Vec<_>>();
+        assert_eq!(children.len() - 2, lost.len());
+        assert!(lost.iter().all(|c| c.visits > 0));
+    }
+
+    #[test]
+    fn self_play_game() {
+        let player = MCTSPlayer::with_budget(Budget::Iterations(50));
+        let config = Config { size: 5, blocks: 2, random_plies: 2 };
+        let samples = play_game(&player, &config, &mut rand::thread_rng());
+        assert!(!samples.is_empty());
+        for pair in samples.windows(2) {
+            assert_ne!(pair[0].board.active(), pair[1].board.active());
+            assert_eq!(1.0, pair[0].result + pair[1].result);
+        }
+        for sample in &samples {
+            assert!(!sample.children.is_empty());
+            assert_eq!(GameState::Ongoing, sample.board.state());
+        }
+        let closed = Config { size: 4, blocks: 16, random_plies: 0 };
+        assert!(play_game(&player, &closed, &mut rand::thread_rng()).is_empty());
+    }
+}
@@ -0,0 +1,544 @@
+// A small HTTP/1.1 server for playing against the engine from a browser.
+// Requests and responses carry JSON; every connection serves one request.
+// Each game wraps a protocol `Engine`, so games run independently and a
+// long search only holds up its own game.
+//
+//   GET    /games                   = ids of the games in memory
+//   POST   /games                   create a game from
+//                                   {"size", "blocks", "seed", "position",
+//                                    "playouts", "time_ms"}, all optional
+//   GET    /games/ID                = game state
+//   POST   /games/ID/move           play {"move": "N4"}
+//   POST   /games/ID/engine         let the engine choose and play a move
+//   POST   /games/ID/undo           take back the last move
+//   DELETE /games/ID                forget the game
+//
+// Everything but the list and DELETE answers with the game state; errors
+// answer with {"error": message}. OPTIONS answers CORS preflight requests.
+// Once MAX_GAMES games are in memory, creating another forgets the one left
+// alone the longest.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{SeedableRng, StdRng};
+
+use board::{Board, Entry};
+use player::{Budget, MCTSPlayer};
+use protocol::Engine;
+
+const MAX_BODY: usize = 1 << 16;
+const MAX_LINE: usize = 1 << 13;
+const MAX_HEADERS: usize = 100;
+const MAX_SIZE: usize = 32;
+const MAX_PLAYOUTS: usize = 1_000_000;
+const MAX_TIME_MS: u64 = 60_000;
+const MAX_GAMES: usize = 1000;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+// Parses a flat JSON object, which is all the API takes as input.
+pub fn parse_object(s: &str) -> Result<BTreeMap<String, Value>, String> {
+    let mut p = Parser { chars: s.chars().peekable() };
+    let mut object = BTreeMap::new();
+    p.expect('{')?;
+    if !p.eat('}') {
+        loop {
+            let key = p.string()?;
+            p.expect(':')?;
+            let value = p.value()?;
+            object.insert(key, value);
+            if p.eat('}') { break; }
+            p.expect(',')?;
+        }
+    }
+    p.skip_whitespace();
+    match p.chars.next() {
+        None => Ok(object),
+        Some(c) => Err(format!("unexpected `{}`", c)),
+    }
+}
+
+struct Parser<'a> {
+    chars: ::std::iter::Peekable<::std::str::Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) { self.chars.next(); }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&c) {
+            self.chars.next();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) { Ok(()) } else { Err(format!("expected `{}`", c)) }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(String::from("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => s.push(c),
+                    _ => return Err(String::from("unsupported escape")),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('"') => return self.string().map(Value::String),
+            Some(..) => (),
+            None => return Err(String::from("expected a value")),
+        }
+        let mut word = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_alphanumeric() || c == '-' || c == '+' || c == '.') { break; }
+            word.push(c);
+            self.chars.next();
+        }
+        match word.as_str() {
+            "null" => Ok(Value::Null),
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => word.parse().map(Value::Number).map_err(|_| format!("invalid value `{}`", word)),
+        }
+    }
+}
+
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn list<T, F: Fn(&T) -> String>(items: &[T], f: F) -> String {
+    format!("[{}]", items.iter().map(f).collect::<Vec<_>>().join(","))
+}
+
+fn player_name(entry: Entry) -> &'static str {
+    match entry {
+        Entry::Player1 => "x",
+        Entry::Player2 => "o",
+        _ => "",
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+// Reads one line of at most MAX_LINE bytes; returns an empty string at end of input.
+fn read_line<R: BufRead>(r: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    io::Read::take(&mut *r, MAX_LINE as u64 + 1).read_line(&mut line)?;
+    if line.len() > MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long"));
+    }
+    Ok(line)
+}
+
+pub fn read_request<R: BufRead>(r: &mut R) -> io::Result<Request> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let line = read_line(r)?;
+    let mut words = line.split_whitespace();
+    let (method, path) = match (words.next(), words.next()) {
+        (Some(method), Some(path)) => (String::from(method), String::from(path)),
+        _ => return Err(invalid("invalid request line")),
+    };
+    let mut length = 0;
+    for i in 0.. {
+        if i == MAX_HEADERS { return Err(invalid("too many headers")); }
+        let header = read_line(r)?;
+        if header.is_empty() { break; }
+        let header = header.trim();
+        if header.is_empty() { break; }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap().trim().to_lowercase();
+        if name == "content-length" {
+            length = parts.next().and_then(|v| v.trim().parse().ok())
+                .ok_or_else(|| invalid("invalid content length"))?;
+        }
+    }
+    if length > MAX_BODY { return Err(invalid("request body too large")); }
+    let mut body = vec![0; length];
+    r.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|_| invalid("request body is not UTF-8"))?;
+    Ok(Request { method, path, body })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Response { Response { status: 200, body } }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response { status, body: format!("{{\"error\":{}}}", quote(message)) }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            _ => "Internal Server Error",
+        };
+        write!(w, "HTTP/1.1 {} {}\r\n", self.status, reason)?;
+        if self.status != 204 {
+            write!(w, "Content-Type: application/json\r\n")?;
+            write!(w, "Content-Length: {}\r\n", self.body.len())?;
+        }
+        write!(w, "Access-Control-Allow-Origin: *\r\n")?;
+        write!(w, "Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n")?;
+        write!(w, "Access-Control-Allow-Headers: Content-Type\r\n")?;
+        write!(w, "Connection: close\r\n\r\n{}", self.body)?;
+        w.flush()
+    }
+}
+
+// A game and when a request last asked for it.
+struct Slot {
+    engine: Arc<Mutex<Engine>>,
+    used: Instant,
+}
+
+struct Games {
+    next: u64,
+    games: HashMap<u64, Slot>,
+}
+
+pub struct Server {
+    player: MCTSPlayer,
+    games: Mutex<Games>,
+}
+
+fn state(id: u64, engine: &Engine) -> String {
+    let b = engine.board();
+    let result = match b.outcome() {
+        Some(outcome) => quote(&outcome.to_string()),
+        None => String::from("null"),
+    };
+    let rows = (0..b.size()).map(|row| {
+        let cells = (0..b.size()).map(|col| match b.get(row, col) {
+            Some(Entry::Block) => '#',
+            Some(Entry::Player1) => 'x',
+            Some(Entry::Player2) => 'o',
+            _ => '.',
+        }).collect::<String>();
+        quote(&cells)
+    }).collect::<Vec<_>>();
+    let legal = b.legal_moves_iter().map(|m| m.to_string()).collect::<Vec<_>>();
+    let moves = engine.history().iter().map(|m| m.to_string()).collect::<Vec<_>>();
+    let lines = list(b.winning_lines(), |line| list(line, |&(r, c)| format!("[{},{}]", r, c)));
+    let search = match engine.info() {
+        Some(info) => format!("{{\"move\":{},\"eval\":{},\"playouts\":{},\"ms\":{}}}",
+                              quote(&info.m.to_string()), quote(&info.eval.to_string()),
+                              info.nplay, info.elapsed.as_millis()),
+        None => String::from("null"),
+    };
+    format!("{{\"id\":{},\"size\":{},\"position\":{},\"rows\":[{}],\"to_move\":{},\
+             \"result\":{},\"moves\":{},\"legal\":{},\"winning_lines\":{},\"search\":{}}}",
+            id, b.size(), quote(&b.to_notation()), rows.join(","), quote(player_name(b.active())),
+            result, list(&moves, |m| quote(m)), list(&legal, |m| quote(m)), lines, search)
+}
+
+fn number(fields: &BTreeMap<String, Value>, name: &str) -> Result<Option<f64>, String> {
+    match fields.get(name) {
+        None | Some(&Value::Null) => Ok(None),
+        Some(&Value::Number(x)) if x >= 0.0 && x.fract() == 0.0 => Ok(Some(x)),
+        Some(..) => Err(format!("{}: expected a non-negative integer", name)),
+    }
+}
+
+impl Server {
+    // Each game gets a copy of `player`, with the budget the game asks for.
+    pub fn new(player: MCTSPlayer) -> Server {
+        Server {
+            player,
+            games: Mutex::new(Games { next: 1, games: HashMap::new() }),
+        }
+    }
+
+    fn create(&self, body: &str) -> Result<Response, Response> {
+        let body = if body.trim().is_empty() { "{}" } else { body };
+        let bad = |e: String| Response::error(400, &e);
+        let fields = parse_object(body).map_err(bad)?;
+        let b = match fields.get("position") {
+            Some(Value::String(position)) => {
+                let b = position.parse::<Board>()
+                    .map_err(|e| bad(e.to_string().trim_start_matches("Error: ").to_string()))?;
+                if !(4..=MAX_SIZE).contains(&b.size()) {
+                    return Err(bad(format!("position: size must be between 4 and {}", MAX_SIZE)));
+                }
+                b
+            },
+            Some(..) => return Err(bad(String::from("position: expected a string"))),
+            None => {
+                let size = number(&fields, "size").map_err(bad)?.unwrap_or(10.0) as usize;
+                let blocks = number(&fields, "blocks").map_err(bad)?.unwrap_or(6.0) as usize;
+                if !(4..=MAX_SIZE).contains(&size) {
+                    return Err(bad(format!("size: must be between 4 and {}", MAX_SIZE)));
+                }
+                if blocks > size * size { return Err(bad(String::from("blocks: too many"))); }
+                match number(&fields, "seed").map_err(bad)? {
+                    Some(seed) => {
+                        let mut rng: StdRng = SeedableRng::from_seed(&[seed as usize][..]);
+                        Board::generate_with_rng(&mut rng, size, blocks)
+                    },
+                    None => Board::generate(size, blocks),
+                }
+            },
+        };
+        let playouts = number(&fields, "playouts").map_err(bad)?;
+        let time_ms = number(&fields, "time_ms").map_err(bad)?;
+        let budget = match (playouts, time_ms) {
+            (Some(n), _) => Budget::Iterations((n as usize).clamp(1, MAX_PLAYOUTS)),
+            (None, Some(ms)) => {
+                Budget::Time(Duration::from_millis((ms as u64).clamp(1, MAX_TIME_MS)))
+            },
+            (None, None) => self.player.budget(),
+        };
+        let mut player = self.player.clone();
+        player.set_budget(budget);
+        let mut engine = Engine::new(player);
+        engine.execute(&format!("position {}", b.to_notation())).map_err(bad)?;
+        let mut games = self.games.lock().unwrap();
+        if games.games.len() >= MAX_GAMES {
+            let idle = games.games.iter().min_by_key(|&(_, slot)| slot.used).map(|(&id, _)| id);
+            if let Some(id) = idle { games.games.remove(&id); }
+        }
+        let id = games.next;
+        games.next += 1;
+        let response = Response { status: 201, body: state(id, &engine) };
+        let slot = Slot { engine: Arc::new(Mutex::new(engine)), used: Instant::now() };
+        games.games.insert(id, slot);
+        Ok(response)
+    }
+
+    fn game(&self, id: &str) -> Result<(u64, Arc<Mutex<Engine>>), Response> {
+        let not_found = || Response::error(404, "no such game");
+        let id = id.parse::<u64>().map_err(|_| not_found())?;
+        let mut games = self.games.lock().unwrap();
+        let slot = games.games.get_mut(&id).ok_or_else(not_found)?;
+        slot.used = Instant::now();
+        Ok((id, slot.engine.clone()))
+    }
+
+    // Runs a protocol command on a game. Only the game's own lock is held,
+    // so a search does not hold up requests for other games.
+    fn command(&self, id: &str, command: &str) -> Result<Response, Response> {
+        let (id, game) = self.game(id)?;
+        let mut engine = game.lock().unwrap();
+        engine.execute(command).map_err(|e| {
+            Response::error(if e == "game over" { 409 } else { 400 }, &e)
+        })?;
+        Ok(Response::ok(state(id, &engine)))
+    }
+
+    fn route(&self, req: &Request) -> Result<Response, Response> {
+        let path = req.path.split('?').next().unwrap();
+        let parts = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        let method = req.method.as_str();
+        match (method, &parts[..]) {
+            ("OPTIONS", _) => Ok(Response { status: 204, body: String::new() }),
+            ("GET", ["games"]) => {
+                let games = self.games.lock().unwrap();
+                let mut ids = games.games.keys().cloned().collect::<Vec<_>>();
+                ids.sort();
+                Ok(Response::ok(format!("{{\"games\":{}}}", list(&ids, |id| id.to_string()))))
+            },
+            ("POST", ["games"]) => self.create(&req.body),
+            ("GET", ["games", id]) => {
+                let (id, game) = self.game(id)?;
+                let engine = game.lock().unwrap();
+                Ok(Response::ok(state(id, &engine)))
+            },
+            ("DELETE", ["games", id]) => {
+                let (id, _) = self.game(id)?;
+                self.games.lock().unwrap().games.remove(&id);
+                Ok(Response::ok(String::from("{}")))
+            },
+            ("POST", ["games", id, "move"]) => {
+                let fields = parse_object(&req.body).map_err(|e| Response::error(400, &e))?;
+                let m = match fields.get("move") {
+                    Some(Value::String(m)) if !m.contains(char::is_whitespace) => m.clone(),
+                    _ => return Err(Response::error(400, "move: expected a move such as N4")),
+                };
+                self.command(id, &format!("play {}", m))
+            },
+            ("POST", ["games", id, "engine"]) => self.command(id, "genmove"),
+            ("POST", ["games", id, "undo"]) => self.command(id, "undo"),
+            (_, ["games"]) | (_, ["games", _]) | (_, ["games", _, "move"])
+            | (_, ["games", _, "engine"]) | (_, ["games", _, "undo"]) => {
+                Err(Response::error(405, "method not allowed"))
+            },
+            _ => Err(Response::error(404, "not found")),
+        }
+    }
+
+    pub fn handle(&self, req: &Request) -> Response {
+        self.route(req).unwrap_or_else(|e| e)
+    }
+
+    fn connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader) {
+            Ok(req) => self.handle(&req),
+            Err(e) => Response::error(400, &e.to_string()),
+        };
+        response.write_to(&mut &stream)
+    }
+}
+
+// Accepts connections until the listener fails, one thread each.
+pub fn serve(server: Arc<Server>, listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let server = server.clone();
+        thread::spawn(move || {
+            let _ = server.connection(stream);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        Request { method: String::from(method), path: String::from(path), body: String::from(body) }
+    }
+
+    #[test]
+    fn server_parse_object() {
+        let fields = parse_object(r#"{ "size": 6, "move": "N\"4", "x": null, "y": true }"#);
+        let fields = fields.unwrap();
+        assert_eq!(Some(&Value::Number(6.0)), fields.get("size"));
+        assert_eq!(Some(&Value::String(String::from("N\"4"))), fields.get("move"));
+        assert_eq!(Some(&Value::Null), fields.get("x"));
+        assert_eq!(Some(&Value::Bool(true)), fields.get("y"));
+        assert!(parse_object("{}").unwrap().is_empty());
+        assert!(parse_object(r#"{"size": }"#).is_err());
+        assert!(parse_object(r#"{"size": 6} x"#).is_err());
+        assert_eq!("\"a\\\"b\\\\\"", quote("a\"b\\"));
+    }
+
+    #[test]
+    fn server_read_request() {
+        let raw = "POST /games HTTP/1.1\r\nHost: x\r\nContent-Length: 4\r\n\r\n{}  ";
+        let req = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request("POST", "/games", "{}  "), req);
+        assert!(read_request(&mut "\r\n".as_bytes()).is_err());
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert!(read_request(&mut long.as_bytes()).is_err());
+        let many = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS));
+        assert!(read_request(&mut many.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn server_game_flow() {
+        let server = Server::new(MCTSPlayer::with_budget(Budget::Iterations(50)));
+        let created = server.handle(&request("POST", "/games", r#"{"position": "4/4/4/4 x"}"#));
+        assert_eq!(201, created.status);
+        assert!(created.body.starts_with(r#"{"id":1,"size":4,"position":"4/4/4/4 x""#));
+        let moved = server.handle(&request("POST", "/games/1/move", r#"{"move": "n0"}"#));
+        assert!(moved.body.contains(r#""moves":["N0"]"#), "{}", moved.body);
+        assert!(moved.body.contains(r#""rows":["....","....","....","x..."]"#));
+        let engine = server.handle(&request("POST", "/games/1/engine", ""));
+        assert!(engine.body.contains(r#""search":{"move":"#));
+        assert!(engine.body.contains(r#""to_move":"x""#));
+        let illegal = server.handle(&request("POST", "/games/1/move", r#"{"move": "Q0"}"#));
+        assert_eq!(Response::error(400, "invalid side"), illegal);
+        assert_eq!(404, server.handle(&request("GET", "/games/2", "")).status);
+        assert_eq!(405, server.handle(&request("PUT", "/games/1", "")).status);
+        assert_eq!(r#"{"games":[1]}"#, server.handle(&request("GET", "/games", "")).body);
+        assert_eq!(200, server.handle(&request("DELETE", "/games/1", "")).status);
+        assert_eq!(404, server.handle(&request("GET", "/games/1", "")).status);
+        let won = server.handle(&request("POST", "/games", r#"{"position": "x3/x3/x3/4 x"}"#));
+        assert_eq!(201, won.status);
+        server.handle(&request("POST", "/games/2/move", r#"{"move": "S0"}"#));
+        let state = server.handle(&request("GET", "/games/2", ""));
+        assert!(state.body.contains(r#""result":"1-0""#));
+        assert!(state.body.contains(r#""winning_lines":[[[0,0],[1,0],[2,0],[3,0]]]"#));
+        assert_eq!(409, server.handle(&request("POST", "/games/2/engine", "")).status);
+        assert_eq!(400, server.handle(&request("POST", "/games", r#"{"size": 2}"#)).status);
+        let big = format!(r#"{{"position": "{} x"}}"#, vec!["33"; 33].join("/"));
+        assert_eq!(400, server.handle(&request("POST", "/games", &big)).status);
+        let small = request("POST", "/games", r#"{"position": "3/3/3 x"}"#);
+        assert_eq!(400, server.handle(&small).status);
+    }
+
+    #[test]
+    fn server_preflight() {
+        let server = Server::new(MCTSPlayer::with_budget(Budget::Iterations(50)));
+        let response = server.handle(&request("OPTIONS", "/games/1/move", ""));
+        assert_eq!(204, response.status);
+        let mut raw = Vec::new();
+        response.write_to(&mut raw).unwrap();
+        let raw = String::from_utf8(raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 204 No Content\r\n"));
+        assert!(raw.contains("Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n"));
+        assert!(raw.contains("Access-Control-Allow-Headers: Content-Type\r\n"));
+        assert!(!raw.contains("Content-Length"));
+    }
+
+    #[test]
+    fn server_evicts_idle_games() {
+        let server = Server::new(MCTSPlayer::with_budget(Budget::Iterations(50)));
+        let create = || server.handle(&request("POST", "/games", r#"{"position": "4/4/4/4 x"}"#));
+        for _ in 0..MAX_GAMES { assert_eq!(201, create().status); }
+        assert_eq!(200, server.handle(&request("GET", "/games/1", "")).status);
+        assert_eq!(201, create().status);
+        assert_eq!(MAX_GAMES, server.games.lock().unwrap().games.len());
+        assert_eq!(200, server.handle(&request("GET", "/games/1", "")).status);
+        assert_eq!(404, server.handle(&request("GET", "/games/2", "")).status);
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt;
+
+use rand::Rng;
+
+use board::{Board, Entry};
+use game::Game;
+use player::{Player, PlayerError};
+
+// Wins, draws and losses from the point of view of the first player of a
+// match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Elo {
+    pub diff: f64,
+    pub margin: f64,
+}
+
+fn elo_for(p: f64) -> f64 {
+    -400.0 * (1.0 / p - 1.0).log10()
+}
+
+fn expected_for(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Score {
+    pub fn games(&self) -> usize { self.wins + self.draws + self.losses }
+
+    pub fn add(&mut self, outcome: Option<Entry>, player: Entry) {
+        match outcome {
+            Some(winner) if winner == player => self.wins += 1,
+            Some(..) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+
+    pub fn reversed(&self) -> Score {
+        Score { wins: self.losses, draws: self.draws, losses: self.wins }
+    }
+
+    // Mean and variance of the per-game score, with `prior` pseudo-games of
+    // each result mixed in.
+    fn moments(&self, prior: f64) -> (f64, f64) {
+        let (w, d, l) = (self.wins as f64 + prior, self.draws as f64 + prior,
+                         self.losses as f64 + prior);
+        let n = w + d + l;
+        let p = (w + 0.5 * d) / n;
+        let var = (w * (1.0 - p).powi(2) + d * (0.5 - p).powi(2) + l * p.powi(2)) / n;
+        (p, var)
+    }
+
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+
+    // Elo difference with a 95% confidence interval.
+    pub fn elo(&self) -> Option<Elo> {
+        let n = self.games() as f64;
+        if n == 0.0 { return None; }
+        let (p, var) = self.moments(0.0);
+        let se = (var / n).sqrt();
+        let lo = elo_for((p - 1.96 * se).max(0.0));
+        let hi = elo_for((p + 1.96 * se).min(1.0));
+        let diff = elo_for(p);
+        let margin = if diff.is_finite() { (hi - lo) / 2.0 } else { f64::INFINITY };
+        Some(Elo { diff, margin })
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)
+    }
+}
+
+impl fmt::Display for Elo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:+.1} ± {:.1}", self.diff, self.margin)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hypothesis {
+    H0,
+    H1,
+}
+
+// Sequential probability ratio test of H0: elo = elo0 against H1: elo = elo1,
+// using the normal approximation to the log-likelihood ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn llr(&self, score: &Score) -> f64 {
+        let n = score.games() as f64;
+        if n == 0.0 { return 0.0; }
+        // Jeffrey's prior keeps the variance positive for one-sided results.
+        let (p, var) = score.moments(0.5);
+        let (s0, s1) = (expected_for(self.elo0), expected_for(self.elo1));
+        n * (s1 - s0) * (2.0 * p - s0 - s1) / (2.0 * var)
+    }
+
+    pub fn decision(&self, score: &Score) -> Option<Hypothesis> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(Hypothesis::H1)
+        } else if llr <= lower {
+            Some(Hypothesis::H0)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub size: usize,
+    pub blocks: usize,
+    pub pairs: usize,
+    pub sprt: Option<Sprt>,
+}
+
+// Games between two players in pairs: each pair shares a freshly generated
+// block layout, and each player moves first in one of the two games. A game
+// aborted by a player's error stops the match.
+pub struct Match {
+    players: Option<[Box<dyn Player>; 2]>,
+    config: Config,
+}
+
+impl Match {
+    pub fn new(a: Box<dyn Player>, b: Box<dyn Player>, config: Config) -> Match {
+        Match { players: Some([a, b]), config }
+    }
+
+    pub fn into_players(self) -> [Box<dyn Player>; 2] { self.players.unwrap() }
+
+    fn play(&mut self, b: Board, swapped: bool) -> Result<Option<Entry>, PlayerError> {
+        let [a, b1] = self.players.take().unwrap();
+        let players = if swapped { [b1, a] } else { [a, b1] };
+        let mut game = Game::new(b, players);
+        game.run(|_, _| ());
+        let winner = game.winner();
+        let error = game.take_error();
+        let [p1, p2] = game.into_players();
+        self.players = Some(if swapped { [p2, p1] } else { [p1, p2] });
+        error.map_or(Ok(winner), Err)
+    }
+
+    pub fn run<R, F>(&mut self, rng: &mut R, mut on_game: F) -> Result<Score, PlayerError>
+        where R: Rng, F: FnMut(&Score)
+    {
+        let mut score = Score::default();
+        for _ in 0..self.config.pairs {
+            let b = Board::generate_with_rng(rng, self.config.size, self.config.blocks);
+            let winner = self.play(b.clone(), false)?;
+            score.add(winner, Entry::Player1);
+            on_game(&score);
+            let winner = self.play(b, true)?;
+            score.add(winner, Entry::Player2);
+            on_game(&score);
+            if let Some(sprt) = self.config.sprt {
+                if sprt.decision(&score).is_some() { break; }
+            }
+        }
+        Ok(score)
+    }
+}
+
+// `table[i][j]` is the score of player `i` against player `j`.
+pub type Table = Vec<Vec<Score>>;
+
+// Plays a match between every pair of players, returning them with a table
+// of scores.
+pub fn round_robin<R, F>(players: Vec<Box<dyn Player>>, config: Config, rng: &mut R,
+                         mut on_game: F)
+                         -> Result<(Vec<Box<dyn Player>>, Table), PlayerError>
+    where R: Rng, F: FnMut(usize, usize, &Score)
+{
+    let n = players.len();
+    let mut players = players.into_iter().map(Some).collect::<Vec<_>>();
+    let mut table = vec![vec![Score::default(); n]; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (a, b) = (players[i].take().unwrap(), players[j].take().unwrap());
+            let mut m = Match::new(a, b, config);
+            let score = m.run(rng, |score| on_game(i, j, score))?;
+            let [a, b] = m.into_players();
+            players[i] = Some(a);
+            players[j] = Some(b);
+            table[i][j] = score;
+            table[j][i] = score.reversed();
+        }
+    }
+    Ok((players.into_iter().map(Option::unwrap).collect(), table))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+    use player::{Decision, RandomPlayer};
+
+    struct Broken;
+
+    impl Player for Broken {
+        fn choose(&mut self, _b: &Board) -> Result<Decision, PlayerError> {
+            Err(PlayerError::Engine(String::from("broken")))
+        }
+    }
+
+    #[test]
+    fn score_elo() {
+        let even = Score { wins: 10, draws: 5, losses: 10 };
+        let elo = even.elo().unwrap();
+        assert_eq!(0.0, elo.diff);
+        assert!(elo.margin > 0.0);
+        let better = Score { wins: 60, draws: 0, losses: 40 };
+        let elo = better.elo().unwrap();
+        assert!((elo.diff - 70.4).abs() < 0.1, "{}", elo);
+        assert!((elo.margin - 70.0).abs() < 1.0, "{}", elo);
+        assert!((elo.diff + better.reversed().elo().unwrap().diff).abs() < 1e-9);
+        assert_eq!(None, Score::default().elo());
+        let elo = Score { wins: 3, draws: 0, losses: 0 }.elo().unwrap();
+        assert_eq!(Elo { diff: f64::INFINITY, margin: f64::INFINITY }, elo);
+    }
+
+    #[test]
+    fn sprt_decision() {
+        let sprt = Sprt::new(0.0, 10.0);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+        assert_eq!(None, sprt.decision(&Score { wins: 5, draws: 0, losses: 5 }));
+        assert_eq!(Some(Hypothesis::H1), sprt.decision(&Score { wins: 100, draws: 0, losses: 0 }));
+        assert_eq!(Some(Hypothesis::H0), sprt.decision(&Score { wins: 0, draws: 0, losses: 100 }));
+    }
+
+    #[test]
+    fn match_run() {
+        let config = Config { size: 5, blocks: 2, pairs: 3, sprt: None };
+        let mut m = Match::new(Box::new(RandomPlayer), Box::new(RandomPlayer), config);
+        let mut ngames = 0;
+        let score = m.run(&mut rand::thread_rng(), |score| {
+            ngames += 1;
+            assert_eq!(ngames, score.games());
+        }).unwrap();
+        assert_eq!(6, score.games());
+        let config = Config { size: 5, blocks: 2, pairs: 1, sprt: None };
+        let players: Vec<Box<dyn Player>> =
+            vec![Box::new(RandomPlayer), Box::new(RandomPlayer), Box::new(RandomPlayer)];
+        let (players, table) =
+            round_robin(players, config, &mut rand::thread_rng(), |_, _, _| ()).unwrap();
+        assert_eq!(3, players.len());
+        assert_eq!(0, table[1][1].games());
+        assert_eq!(2, table[0][2].games());
+        assert_eq!(table[0][2], table[2][0].reversed());
+    }
+
+    #[test]
+    fn match_stops_on_error() {
+        let config = Config { size: 5, blocks: 2, pairs: 3, sprt: None };
+        let mut m = Match::new(Box::new(RandomPlayer), Box::new(Broken), config);
+        let mut ngames = 0;
+        assert!(m.run(&mut rand::thread_rng(), |_| ngames += 1).is_err());
+        assert_eq!(0, ngames);
+    }
+}
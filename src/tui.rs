@@ -0,0 +1,356 @@
+// A full-screen terminal interface. The human picks an entry point by moving
+// a marker around the edge of the board with the arrow keys, sees where the
+// piece would land, and presses enter to play it. Drawing uses ANSI escapes
+// and raw input comes from `stty`, so no terminal library is needed.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
+
+use board::{Board, Entry, LegalMove, Move, Side};
+use player::{Budget, Decision, MCTSPlayer, Player, PlayerError};
+
+const CLEAR: &str = "\x1b[H\x1b[2J";
+const RESET: &str = "\x1b[0m";
+const REVERSE: &str = "\x1b[7m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+
+const KEYS: &str = "arrows: move  tab: next side  enter: play  \
+                    u: undo  h: hint  d: draw  r: resign  q: quit";
+
+// Puts the terminal in raw mode on the alternate screen until dropped.
+pub struct RawMode {
+    saved: String,
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::from(File::open("/dev/tty")?))
+        .stderr(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl RawMode {
+    pub fn enter() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Tab,
+    Enter,
+    Char(char),
+    Eof,
+}
+
+fn read_byte<R: Read>(r: &mut R) -> io::Result<Option<u8>> {
+    let mut buf = [0u8; 1];
+    match r.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0])),
+    }
+}
+
+pub fn read_key<R: Read>(r: &mut R) -> io::Result<Key> {
+    loop {
+        let key = match read_byte(r)? {
+            None => Key::Eof,
+            Some(b'\x1b') => {
+                if read_byte(r)? != Some(b'[') { continue; }
+                match read_byte(r)? {
+                    Some(b'A') => Key::Up,
+                    Some(b'B') => Key::Down,
+                    Some(b'C') => Key::Right,
+                    Some(b'D') => Key::Left,
+                    None => Key::Eof,
+                    _ => continue,
+                }
+            },
+            Some(b'\t') => Key::Tab,
+            Some(b'\r') | Some(b'\n') | Some(b' ') => Key::Enter,
+            Some(c) => Key::Char(c as char),
+        };
+        return Ok(key);
+    }
+}
+
+// An entry point on the edge of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub side: Side,
+    pub pos: usize,
+}
+
+impl Cursor {
+    pub fn new(size: usize) -> Cursor {
+        Cursor { side: Side::North, pos: size / 2 }
+    }
+
+    pub fn base(&self) -> Move { Move::new(self.side, self.pos) }
+
+    // Moves along the current edge, turning the corner at either end.
+    pub fn step(&mut self, key: Key, size: usize) {
+        let last = size - 1;
+        let (side, pos) = match (self.side, key, self.pos) {
+            (Side::North, Key::Left, 0) => (Side::West, 0),
+            (Side::North, Key::Left, p) => (Side::North, p - 1),
+            (Side::North, Key::Right, p) if p == last => (Side::East, 0),
+            (Side::North, Key::Right, p) => (Side::North, p + 1),
+            (Side::South, Key::Left, 0) => (Side::West, last),
+            (Side::South, Key::Left, p) => (Side::South, p - 1),
+            (Side::South, Key::Right, p) if p == last => (Side::East, last),
+            (Side::South, Key::Right, p) => (Side::South, p + 1),
+            (Side::East, Key::Up, 0) => (Side::North, last),
+            (Side::East, Key::Up, p) => (Side::East, p - 1),
+            (Side::East, Key::Down, p) if p == last => (Side::South, last),
+            (Side::East, Key::Down, p) => (Side::East, p + 1),
+            (Side::West, Key::Up, 0) => (Side::North, 0),
+            (Side::West, Key::Up, p) => (Side::West, p - 1),
+            (Side::West, Key::Down, p) if p == last => (Side::South, 0),
+            (Side::West, Key::Down, p) => (Side::West, p + 1),
+            (side, Key::Tab, p) => (side.succ().unwrap_or(Side::North), p),
+            (side, _, p) => (side, p),
+        };
+        self.side = side;
+        self.pos = pos;
+    }
+}
+
+// What the screen shows besides the board.
+pub struct Screen {
+    output: Box<dyn Write>,
+    names: [String; 2],
+    status: [String; 2],
+    last: Option<LegalMove>,
+    message: String,
+}
+
+impl Screen {
+    pub fn new(output: Box<dyn Write>) -> Screen {
+        Screen {
+            output,
+            names: [String::new(), String::new()],
+            status: [String::new(), String::new()],
+            last: None,
+            message: String::new(),
+        }
+    }
+
+    pub fn set_names(&mut self, names: [String; 2]) { self.names = names; }
+
+    pub fn set_last(&mut self, m: Option<LegalMove>) { self.last = m; }
+
+    pub fn set_status(&mut self, entry: Entry, status: String) {
+        match entry {
+            Entry::Player1 => self.status[0] = status,
+            Entry::Player2 => self.status[1] = status,
+            _ => (),
+        }
+    }
+
+    pub fn set_message(&mut self, message: &str) { self.message = String::from(message); }
+
+    fn panel(&self, b: &Board) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (i, &entry) in [Entry::Player1, Entry::Player2].iter().enumerate() {
+            let turn = if b.active() == entry && b.outcome().is_none() { "  <" } else { "" };
+            lines.push(format!("{} {}{}", entry, self.names[i], turn));
+            lines.push(format!("  {}", self.status[i]));
+        }
+        lines.push(String::new());
+        if let Some(m) = self.last { lines.push(format!("Last move: {}", m)); }
+        lines
+    }
+
+    pub fn render(&self, b: &Board, cursor: Option<Cursor>) -> String {
+        let size = b.size();
+        let preview = cursor.and_then(|c| c.base().annotated(b));
+        let marker = |side: Side, pos: usize| match cursor {
+            Some(c) if c.side == side && c.pos == pos => match side {
+                Side::North => '▼',
+                Side::East => '◀',
+                Side::South => '▲',
+                Side::West => '▶',
+            },
+            _ => ' ',
+        };
+        let mut lines = Vec::new();
+        let mut header = String::from("   ");
+        for col in 0..size { header.push_str(&format!("{: >2}", col)); }
+        lines.push(header);
+        let mut north = String::from("   ");
+        for col in 0..size { north.push(' '); north.push(marker(Side::North, col)); }
+        lines.push(north);
+        let panel = self.panel(b);
+        for row in 0..size {
+            let mut line = format!("{: >2}{}", row, marker(Side::West, row));
+            for col in 0..size {
+                let entry = b.get(row, col).unwrap();
+                let cell = if preview.map(|m| m.target()) == Some((row, col)) {
+                    format!("{}{}{}", REVERSE, b.active(), RESET)
+                } else if b.winning_lines().iter().any(|l| l.contains(&(row, col))) {
+                    format!("{}{}{}", GREEN, entry, RESET)
+                } else if self.last.map(|m| m.target()) == Some((row, col)) {
+                    format!("{}{}{}", YELLOW, entry, RESET)
+                } else {
+                    entry.to_string()
+                };
+                line.push(' ');
+                line.push_str(&cell);
+            }
+            line.push(' ');
+            line.push(marker(Side::East, row));
+            if let Some(text) = panel.get(row) {
+                line.push_str("   ");
+                line.push_str(text);
+            }
+            lines.push(line);
+        }
+        let mut south = String::from("   ");
+        for col in 0..size { south.push(' '); south.push(marker(Side::South, col)); }
+        lines.push(south);
+        for text in panel.iter().skip(size) { lines.push(text.clone()); }
+        lines.push(self.message.clone());
+        lines.push(String::from(KEYS));
+        lines.join("\r\n")
+    }
+
+    pub fn draw(&mut self, b: &Board, cursor: Option<Cursor>) -> io::Result<()> {
+        let screen = self.render(b, cursor);
+        write!(self.output, "{}{}\r\n", CLEAR, screen)?;
+        self.output.flush()
+    }
+}
+
+// A human seat driven from the keyboard, drawing on a shared `Screen`.
+pub struct TuiPlayer {
+    screen: Rc<RefCell<Screen>>,
+    keys: Box<dyn Read>,
+    cursor: Option<Cursor>,
+    hint: MCTSPlayer,
+}
+
+impl TuiPlayer {
+    pub fn new(screen: Rc<RefCell<Screen>>, keys: Box<dyn Read>) -> TuiPlayer {
+        let hint = MCTSPlayer::new(Duration::from_secs(1));
+        TuiPlayer { screen, keys, cursor: None, hint }
+    }
+
+    // Hints search for one second unless given another budget.
+    pub fn set_hint_budget(&mut self, budget: Budget) {
+        self.hint = MCTSPlayer::with_budget(budget);
+    }
+}
+
+impl Player for TuiPlayer {
+    fn choose(&mut self, b: &Board) -> Result<Decision, PlayerError> {
+        let mut cursor = self.cursor.unwrap_or_else(|| Cursor::new(b.size()));
+        let decision = loop {
+            self.screen.borrow_mut().draw(b, Some(cursor))?;
+            let key = read_key(&mut self.keys)?;
+            self.screen.borrow_mut().set_message("");
+            match key {
+                Key::Eof | Key::Char('q') | Key::Char('\x03') => return Err(PlayerError::Aborted),
+                Key::Enter => match cursor.base().annotated(b) {
+                    Some(m) => break Decision::Move(m),
+                    None => self.screen.borrow_mut().set_message("That entry point is blocked."),
+                },
+                Key::Char('u') => break Decision::Undo,
+                Key::Char('r') => break Decision::Resign,
+                Key::Char('d') => break Decision::OfferDraw,
+                Key::Char('h') => {
+                    self.screen.borrow_mut().set_message("Thinking...");
+                    self.screen.borrow_mut().draw(b, Some(cursor))?;
+                    let info = self.hint.search(b);
+                    cursor = Cursor { side: info.m.base().side(), pos: info.m.base().pos() };
+                    let message = format!("Hint: {} ({})", info.m, info.eval);
+                    self.screen.borrow_mut().set_message(&message);
+                },
+                key => cursor.step(key, b.size()),
+            }
+        };
+        self.cursor = Some(cursor);
+        Ok(decision)
+    }
+
+    fn name(&self) -> String { String::from("Human") }
+
+    fn accept_draw(&mut self, b: &Board) -> bool {
+        self.screen.borrow_mut().set_message("Your opponent offers a draw. Accept (y/n)?");
+        let accepted = loop {
+            if self.screen.borrow_mut().draw(b, None).is_err() { break false; }
+            match read_key(&mut self.keys) {
+                Ok(Key::Char('y')) => break true,
+                Ok(Key::Char('n')) | Ok(Key::Eof) | Err(..) => break false,
+                _ => (),
+            }
+        };
+        self.screen.borrow_mut().set_message("");
+        accepted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tui_read_key() {
+        let mut input = &b"\x1b[A\x1b[C\tq\r\x1bx\x1b[B"[..];
+        let keys = (0..7).map(|_| read_key(&mut input).unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec![Key::Up, Key::Right, Key::Tab, Key::Char('q'), Key::Enter, Key::Down,
+                        Key::Eof], keys);
+    }
+
+    #[test]
+    fn tui_cursor_walks_the_edge() {
+        let mut c = Cursor { side: Side::North, pos: 3 };
+        c.step(Key::Right, 4);
+        assert_eq!(Cursor { side: Side::East, pos: 0 }, c);
+        for _ in 0..4 { c.step(Key::Down, 4); }
+        assert_eq!(Cursor { side: Side::South, pos: 3 }, c);
+        for _ in 0..4 { c.step(Key::Left, 4); }
+        assert_eq!(Cursor { side: Side::West, pos: 3 }, c);
+        for _ in 0..4 { c.step(Key::Up, 4); }
+        assert_eq!(Cursor { side: Side::North, pos: 0 }, c);
+        c.step(Key::Tab, 4);
+        assert_eq!(Cursor { side: Side::East, pos: 0 }, c);
+    }
+
+    #[test]
+    fn tui_render_preview() {
+        let b = "4/4/4/x3 o".parse::<Board>().unwrap();
+        let mut screen = Screen::new(Box::new(io::sink()));
+        screen.set_names([String::from("Human"), String::from("MCTS")]);
+        screen.set_last(b.parse_move("N1").ok());
+        let s = screen.render(&b, Some(Cursor { side: Side::North, pos: 0 }));
+        let lines = s.split("\r\n").collect::<Vec<_>>();
+        assert_eq!("    ▼      ", lines[1]);
+        assert!(lines[4].contains(&format!("{}○{}", REVERSE, RESET)));
+        assert!(lines[2].contains("● Human"));
+        assert!(lines[4].contains("○ MCTS  <"));
+    }
+}
@@ -0,0 +1,75 @@
+extern crate zgc4;
+
+use std::process::Command;
+use std::time::Duration;
+
+use zgc4::board::{Board, Entry};
+use zgc4::game::Game;
+use zgc4::player::{Budget, ExternalPlayer, Player, PlayerError, RandomPlayer};
+
+fn zgc4() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_zgc4"));
+    command.args(["protocol", "--playouts", "200"]);
+    command
+}
+
+fn fake(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[test]
+fn external_plays_game() {
+    let p = ExternalPlayer::spawn(zgc4(), None).unwrap();
+    assert_eq!("zgc4", p.name());
+    let players: [Box<dyn Player>; 2] = [Box::new(p), Box::new(RandomPlayer)];
+    let mut game = Game::new(Board::generate(6, 3), players);
+    game.run(|_, _| ());
+    assert!(game.result().is_some());
+    assert_eq!(Some("zgc4"), game.record().player(Entry::Player1));
+}
+
+#[test]
+fn external_illegal_reply() {
+    let script = r#"while read c; do case "$c" in
+        protocol_version) echo "= 1";; genmove*) echo "= Q7";; *) echo "= fake";;
+    esac; done"#;
+    let mut p = ExternalPlayer::spawn(fake(script), None).unwrap();
+    assert_eq!("fake", p.name());
+    let b = Board::new(6);
+    assert!(p.request(&b).is_err());
+    assert!(p.choose(&b).is_err());
+}
+
+#[test]
+fn external_timeout() {
+    let script = r#"while read c; do case "$c" in
+        protocol_version) echo "= 1";; genmove*) sleep 2; echo "= N0";; *) echo "=";;
+    esac; done"#;
+    let budget = Budget::Time(Duration::from_millis(10));
+    let mut p = ExternalPlayer::spawn(fake(script), Some(budget)).unwrap();
+    p.set_timeout(Duration::from_millis(100));
+    let b = Board::new(6);
+    match p.choose(&b) {
+        Err(PlayerError::Engine(s)) => assert_eq!("engine timed out", s),
+        _ => panic!("expected a timeout"),
+    }
+    assert!(p.choose(&b).is_err());
+}
+
+#[test]
+fn external_playouts() {
+    let script = r#"while read c; do case "$c" in
+        protocol_version) echo "= 1";; "genmove playouts 50") echo "= N0";;
+        genmove*) echo "? wrong budget";; *) echo "=";;
+    esac; done"#;
+    let mut p = ExternalPlayer::spawn(fake(script), Some(Budget::Iterations(50))).unwrap();
+    assert_eq!("N0", p.request(&Board::new(6)).unwrap().to_string());
+}
+
+#[test]
+fn external_bad_handshake() {
+    assert!(ExternalPlayer::spawn(fake("read c; echo '= 99'"), None).is_err());
+    assert!(ExternalPlayer::spawn(fake("exit 0"), None).is_err());
+}
@@ -0,0 +1,114 @@
+extern crate zgc4;
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use zgc4::board::Board;
+use zgc4::book::Book;
+
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn spawn() -> Engine {
+        Engine::spawn_with(&[])
+    }
+
+    fn spawn_with(flags: &[&str]) -> Engine {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_zgc4"))
+            .args(["protocol", "--playouts", "200"])
+            .args(flags)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Engine { child, stdin, stdout }
+    }
+
+    fn send(&mut self, command: &str) -> String {
+        writeln!(self.stdin, "{}", command).unwrap();
+        self.stdin.flush().unwrap();
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        String::from(line.trim_end())
+    }
+}
+
+#[test]
+fn protocol_session() {
+    let mut e = Engine::spawn();
+    assert_eq!("= 1", e.send("protocol_version"));
+    assert_eq!("= zgc4", e.send("name"));
+    assert_eq!("=", e.send("boardsize 4"));
+    assert_eq!("=", e.send("block 1 1"));
+    assert_eq!("= 4/1#2/4/4 x", e.send("showboard"));
+    assert_eq!("= ongoing", e.send("play N0"));
+    assert_eq!("? W3: illegal move", e.send("play W3"));
+    assert_eq!("? invalid side", e.send("play Q0"));
+    assert_eq!("= 4/1#2/4/x3 o", e.send("showboard"));
+    let reply = e.send("genmove playouts 20");
+    assert!(reply.starts_with("= "), "{}", reply);
+    let m = &reply[2..];
+    assert!(e.send("info").starts_with(&format!("= move {} eval ", m)));
+    assert_eq!("=", e.send("undo"));
+    assert_eq!("= 4/1#2/4/x3 o", e.send("showboard"));
+    assert_eq!("=", e.send("reset"));
+    assert_eq!("= 4/1#2/4/4 x", e.send("showboard"));
+    assert_eq!("? no moves", e.send("undo"));
+    assert_eq!("=", e.send("position x3/x3/x3/4 x"));
+    assert_eq!("= won", e.send("play S0"));
+    assert_eq!("? game over", e.send("genmove 10"));
+    assert!(e.send("frobnicate").starts_with("? unknown command"));
+    assert_eq!("=", e.send("boardsize 4"));
+    for cell in &["0 0", "0 1", "0 2", "0 3", "1 0", "1 3", "2 0", "2 3", "3 0", "3 1", "3 2"] {
+        assert_eq!("=", e.send(&format!("block {}", cell)));
+    }
+    assert_eq!("= E3 S3", e.send("moves"));
+    assert_eq!("=", e.send("block 3 3"));
+    assert_eq!("= ####/#2#/#2#/#### x", e.send("showboard"));
+    assert_eq!("=", e.send("moves"));
+    assert_eq!("? game over", e.send("genmove"));
+    assert_eq!("? game over", e.send("play N1"));
+    assert_eq!("=", e.send("quit"));
+    assert!(e.child.wait().unwrap().success());
+}
+
+#[test]
+fn protocol_self_play() {
+    let mut e = Engine::spawn();
+    assert_eq!("=", e.send("boardsize 6"));
+    let mut nmoves = 0;
+    loop {
+        let reply = e.send("genmove");
+        if reply == "? game over" { break; }
+        assert!(reply.starts_with("= "), "{}", reply);
+        nmoves += 1;
+        assert!(nmoves <= 36);
+    }
+    assert_eq!("=", e.send("quit"));
+    assert!(e.child.wait().unwrap().success());
+}
+
+#[test]
+fn protocol_book() {
+    let b = "4/1#2/3x/4 o".parse::<Board>().unwrap();
+    let m = b.parse_move("E3").unwrap();
+    let mut book = Book::new();
+    book.add(&b, m.base(), 1);
+    let path = env::temp_dir().join(format!("zgc4-protocol-book-{}", process::id()));
+    book.save(&path).unwrap();
+    let mut e = Engine::spawn_with(&["--book", path.to_str().unwrap(), "--playouts", "1"]);
+    assert_eq!("=", e.send("position 4/1#2/3x/4 o"));
+    fs::remove_file(&path).unwrap();
+    assert_eq!("= E3", e.send("genmove"));
+    assert_eq!("? no search yet", e.send("info"));
+    assert_eq!("=", e.send("quit"));
+    assert!(e.child.wait().unwrap().success());
+}
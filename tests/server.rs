@@ -0,0 +1,77 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    fn spawn() -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_zgc4"))
+            .args(["serve", "--port", "0", "--playouts", "200"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let addr = String::from(line.trim().trim_start_matches("Listening on http://"));
+        Server { child, addr }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn request(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+           method, path, addr, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+    (status, String::from(body))
+}
+
+#[test]
+fn server_concurrent_games() {
+    let server = Server::spawn();
+    let threads = (0..2).map(|i| {
+        let addr = server.addr.clone();
+        thread::spawn(move || {
+            let body = format!(r#"{{"size": 6, "blocks": 3, "seed": {}}}"#, i);
+            let (status, state) = request(&addr, "POST", "/games", &body);
+            assert_eq!(201, status, "{}", state);
+            let id = state[6..].split(',').next().unwrap().to_string();
+            let mut moves = 0;
+            while !state_of(&addr, &id).contains(r#""legal":[]"#) && moves < 36 {
+                let (status, state) = request(&addr, "POST", &format!("/games/{}/engine", id), "");
+                if status == 409 { break; }
+                assert_eq!(200, status, "{}", state);
+                moves += 1;
+            }
+            assert!(moves > 0);
+            let state = state_of(&addr, &id);
+            assert!(!state.contains(r#""result":null"#), "{}", state);
+            id
+        })
+    }).collect::<Vec<_>>();
+    let mut ids = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(vec!["1", "2"], ids);
+    let games = request(&server.addr, "GET", "/games", "");
+    assert_eq!((200, String::from(r#"{"games":[1,2]}"#)), games);
+}
+
+fn state_of(addr: &str, id: &str) -> String {
+    let (status, state) = request(addr, "GET", &format!("/games/{}", id), "");
+    assert_eq!(200, status);
+    state
+}